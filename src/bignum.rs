@@ -0,0 +1,715 @@
+use std::{cmp::Ordering, fmt, ops};
+
+/// digits kept after the point when a decimal quotient doesn't terminate
+pub const DIVISION_SCALE: u32 = 28;
+
+/// most digits a decimal keeps after the point, and the largest exponent a decimal literal takes,
+/// so lining two decimals up never multiplies by more than 10^MAX_SCALE
+pub const MAX_SCALE: u32 = 10_000;
+
+/// arbitrary-precision integer as a sign and a magnitude of base 2^32 limbs, least significant first
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BigInt {
+  negative: bool,      // never set for zero
+  magnitude: Vec<u32>, // no most significant zero limbs, so zero is empty
+}
+
+fn trim(mut magnitude: Vec<u32>) -> Vec<u32> {
+  while magnitude.last() == Some(&0) {
+    magnitude.pop();
+  }
+  magnitude
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+  a.len()
+    .cmp(&b.len())
+    .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+  let mut sum = Vec::with_capacity(long.len() + 1);
+  let mut carry = 0;
+  for (i, limb) in long.iter().enumerate() {
+    let total = *limb as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+    sum.push(total as u32);
+    carry = total >> 32;
+  }
+  if carry > 0 {
+    sum.push(carry as u32);
+  }
+  sum
+}
+
+/// `a - b`, `a` can't be smaller than `b`
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let mut difference = Vec::with_capacity(a.len());
+  let mut borrow = 0;
+  for (i, limb) in a.iter().enumerate() {
+    let mut d = *limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+    borrow = 0;
+    if d < 0 {
+      d += 1 << 32;
+      borrow = 1;
+    }
+    difference.push(d as u32);
+  }
+  trim(difference)
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+  let mut product = vec![0u32; a.len() + b.len()];
+  for (i, x) in a.iter().enumerate() {
+    let mut carry = 0;
+    for (j, y) in b.iter().enumerate() {
+      let total = *x as u64 * *y as u64 + product[i + j] as u64 + carry;
+      product[i + j] = total as u32;
+      carry = total >> 32;
+    }
+    // nothing has been written this far up yet
+    product[i + b.len()] = carry as u32;
+  }
+  trim(product)
+}
+
+/// `a * factor + addend` for single limb operands
+fn mul_add_small(a: &[u32], factor: u32, addend: u32) -> Vec<u32> {
+  let mut result = Vec::with_capacity(a.len() + 1);
+  let mut carry = addend as u64;
+  for limb in a {
+    let total = *limb as u64 * factor as u64 + carry;
+    result.push(total as u32);
+    carry = total >> 32;
+  }
+  if carry > 0 {
+    result.push(carry as u32);
+  }
+  trim(result)
+}
+
+/// truncated `a / divisor` and `a % divisor` for a single limb divisor
+fn divrem_small(a: &[u32], divisor: u32) -> (Vec<u32>, u32) {
+  let mut quotient = vec![0u32; a.len()];
+  let mut remainder = 0u64;
+  for i in (0..a.len()).rev() {
+    let current = (remainder << 32) | a[i] as u64;
+    quotient[i] = (current / divisor as u64) as u32;
+    remainder = current % divisor as u64;
+  }
+  (trim(quotient), remainder as u32)
+}
+
+/// truncated `a / b` and `a % b`, `b` can't be zero
+fn divrem_magnitude(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+  if cmp_magnitude(a, b) == Ordering::Less {
+    return (vec![], a.to_vec());
+  }
+  if b.len() == 1 {
+    let (quotient, remainder) = divrem_small(a, b[0]);
+    return (quotient, trim(vec![remainder]));
+  }
+
+  // long division in base 2, one bit of the quotient at a time
+  let mut quotient = vec![0u32; a.len()];
+  let mut remainder: Vec<u32> = vec![];
+  for bit in (0..a.len() * 32).rev() {
+    let mut carry = (a[bit / 32] >> (bit % 32)) & 1;
+    for limb in remainder.iter_mut() {
+      let top = *limb >> 31;
+      *limb = (*limb << 1) | carry;
+      carry = top;
+    }
+    if carry > 0 {
+      remainder.push(carry);
+    }
+
+    if cmp_magnitude(&remainder, b) != Ordering::Less {
+      remainder = sub_magnitude(&remainder, b);
+      quotient[bit / 32] |= 1 << (bit % 32);
+    }
+  }
+  (trim(quotient), remainder)
+}
+
+impl BigInt {
+  fn new(negative: bool, magnitude: Vec<u32>) -> Self {
+    let magnitude = trim(magnitude);
+    Self {
+      negative: negative && !magnitude.is_empty(),
+      magnitude,
+    }
+  }
+
+  /// digits in `radix` with an optional leading '-'
+  pub fn parse(text: &str, radix: u32) -> Option<Self> {
+    let (negative, digits) = match text.strip_prefix('-') {
+      Some(digits) => (true, digits),
+      None => (false, text),
+    };
+    if digits.is_empty() {
+      return None;
+    }
+
+    let mut magnitude = vec![];
+    for c in digits.chars() {
+      magnitude = mul_add_small(&magnitude, radix, c.to_digit(radix)?);
+    }
+    Some(Self::new(negative, magnitude))
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.magnitude.is_empty()
+  }
+
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+
+  fn is_odd(&self) -> bool {
+    self.magnitude.first().is_some_and(|limb| limb & 1 == 1)
+  }
+
+  pub fn abs(&self) -> Self {
+    Self::new(false, self.magnitude.clone())
+  }
+
+  /// number of bits in the magnitude
+  pub fn bits(&self) -> u64 {
+    match self.magnitude.last() {
+      Some(top) => self.magnitude.len() as u64 * 32 - top.leading_zeros() as u64,
+      None => 0,
+    }
+  }
+
+  pub fn to_i64(&self) -> Option<i64> {
+    if self.magnitude.len() > 2 {
+      return None;
+    }
+    let value = self
+      .magnitude
+      .iter()
+      .rev()
+      .fold(0i128, |value, limb| (value << 32) | *limb as i128);
+    i64::try_from(if self.negative { -value } else { value }).ok()
+  }
+
+  /// the closest float, infinite if the value is out of range
+  pub fn to_f64(&self) -> f64 {
+    // parsing the digits rounds correctly, which converting limb by limb wouldn't
+    self.to_string().parse().unwrap_or(f64::NAN)
+  }
+
+  pub fn pow(&self, exponent: u32) -> Self {
+    let mut result = Self::from(1);
+    let mut base = self.clone();
+    let mut exponent = exponent;
+    while exponent > 0 {
+      if exponent & 1 == 1 {
+        result = &result * &base;
+      }
+      exponent >>= 1;
+      if exponent > 0 {
+        base = &base * &base;
+      }
+    }
+    result
+  }
+
+  /// floored quotient and remainder, the remainder takes the sign of the divisor like `~/` and `%`
+  /// do for ints; none when dividing by zero
+  pub fn div_floor(&self, divisor: &Self) -> Option<(Self, Self)> {
+    if divisor.is_zero() {
+      return None;
+    }
+
+    let (quotient, remainder) = divrem_magnitude(&self.magnitude, &divisor.magnitude);
+    let quotient = Self::new(self.negative != divisor.negative, quotient);
+    let remainder = Self::new(self.negative, remainder);
+    if !remainder.is_zero() && remainder.negative != divisor.negative {
+      Some((&quotient - &Self::from(1), &remainder + divisor))
+    } else {
+      Some((quotient, remainder))
+    }
+  }
+}
+
+impl From<i64> for BigInt {
+  fn from(n: i64) -> Self {
+    let magnitude = n.unsigned_abs();
+    Self::new(n < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+  }
+}
+
+impl Ord for BigInt {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self.negative, other.negative) {
+      (false, true) => Ordering::Greater,
+      (true, false) => Ordering::Less,
+      (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+      (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+    }
+  }
+}
+
+impl PartialOrd for BigInt {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl ops::Neg for &BigInt {
+  type Output = BigInt;
+
+  fn neg(self) -> BigInt {
+    BigInt::new(!self.negative, self.magnitude.clone())
+  }
+}
+
+impl ops::Add for &BigInt {
+  type Output = BigInt;
+
+  fn add(self, other: &BigInt) -> BigInt {
+    if self.negative == other.negative {
+      return BigInt::new(
+        self.negative,
+        add_magnitude(&self.magnitude, &other.magnitude),
+      );
+    }
+
+    // opposite signs, the larger magnitude decides the sign
+    match cmp_magnitude(&self.magnitude, &other.magnitude) {
+      Ordering::Less => BigInt::new(
+        other.negative,
+        sub_magnitude(&other.magnitude, &self.magnitude),
+      ),
+      _ => BigInt::new(
+        self.negative,
+        sub_magnitude(&self.magnitude, &other.magnitude),
+      ),
+    }
+  }
+}
+
+impl ops::Sub for &BigInt {
+  type Output = BigInt;
+
+  fn sub(self, other: &BigInt) -> BigInt {
+    self + &-other
+  }
+}
+
+impl ops::Mul for &BigInt {
+  type Output = BigInt;
+
+  fn mul(self, other: &BigInt) -> BigInt {
+    BigInt::new(
+      self.negative != other.negative,
+      mul_magnitude(&self.magnitude, &other.magnitude),
+    )
+  }
+}
+
+impl fmt::Display for BigInt {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    if self.is_zero() {
+      return write!(f, "0");
+    }
+
+    // peel off 9 decimal digits at a time, least significant first
+    let mut chunks = vec![];
+    let mut magnitude = self.magnitude.clone();
+    while !magnitude.is_empty() {
+      let (quotient, chunk) = divrem_small(&magnitude, 1_000_000_000);
+      chunks.push(chunk);
+      magnitude = quotient;
+    }
+
+    if self.negative {
+      write!(f, "-")?;
+    }
+    let mut chunks = chunks.iter().rev();
+    write!(f, "{}", chunks.next().unwrap())?;
+    for chunk in chunks {
+      write!(f, "{:09}", chunk)?;
+    }
+    Ok(())
+  }
+}
+
+/// exact base 10 number, `coefficient / 10^scale`. trailing zeros are kept so 1.10 prints as 1.10,
+/// but it's still equal to 1.1
+#[derive(Clone, Debug)]
+pub struct Decimal {
+  coefficient: BigInt,
+  scale: u32, // digits after the point
+}
+
+fn pow10(exponent: u32) -> BigInt {
+  BigInt::from(10).pow(exponent)
+}
+
+impl Decimal {
+  /// digits with an optional '-', point and exponent, e.g. -1.25e3. none when the exponent or the
+  /// digits after the point go past MAX_SCALE
+  pub fn parse(text: &str) -> Option<Self> {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+      Some(e) => (&text[..e], text[e + 1..].parse::<i64>().ok()?),
+      None => (text, 0),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.trim_start_matches('-').is_empty() && fraction.is_empty() {
+      return None;
+    }
+
+    if exponent.unsigned_abs() > u64::from(MAX_SCALE) {
+      return None;
+    }
+
+    let coefficient = BigInt::parse(&format!("{}{}", whole, fraction), 10)?;
+    let scale = fraction.len() as i64 - exponent;
+    if scale < 0 {
+      Some(Self::from(&coefficient * &pow10(-scale as u32)))
+    } else if scale > i64::from(MAX_SCALE) {
+      None
+    } else {
+      Some(Self {
+        coefficient,
+        scale: scale as u32,
+      })
+    }
+  }
+
+  /// the exact value of a finite float, every one of them is a terminating decimal
+  pub fn from_f64(n: f64) -> Option<Self> {
+    if !n.is_finite() {
+      return None;
+    }
+
+    let bits = n.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64;
+    let fraction = bits & ((1 << 52) - 1);
+    // n = mantissa * 2^exponent
+    let (mantissa, exponent) = if exponent == 0 {
+      (fraction, -1074)
+    } else {
+      (fraction | (1 << 52), exponent - 1075)
+    };
+
+    let mut coefficient = BigInt::from(mantissa as i64);
+    if n.is_sign_negative() {
+      coefficient = -&coefficient;
+    }
+    if exponent >= 0 {
+      Some(Self::from(
+        &coefficient * &BigInt::from(2).pow(exponent as u32),
+      ))
+    } else {
+      // m / 2^k == m * 5^k / 10^k
+      let k = -exponent as u32;
+      Some(Self {
+        coefficient: &coefficient * &BigInt::from(5).pow(k),
+        scale: k,
+      })
+    }
+  }
+
+  pub fn is_zero(&self) -> bool {
+    self.coefficient.is_zero()
+  }
+
+  /// number of bits in the coefficient
+  pub fn bits(&self) -> u64 {
+    self.coefficient.bits()
+  }
+
+  /// the coefficient as if there were `scale` digits after the point, `scale` can't be smaller than
+  /// this decimal's
+  fn rescale(&self, scale: u32) -> BigInt {
+    &self.coefficient * &pow10(scale - self.scale)
+  }
+
+  /// both coefficients at the larger of the two scales
+  fn align(&self, other: &Self) -> (BigInt, BigInt, u32) {
+    let scale = self.scale.max(other.scale);
+    (self.rescale(scale), other.rescale(scale), scale)
+  }
+
+  /// drops trailing zeros after the point, keeping at least `min_scale` digits there
+  fn reduce(mut self, min_scale: u32) -> Self {
+    while self.scale > min_scale {
+      let (quotient, digit) = divrem_small(&self.coefficient.magnitude, 10);
+      if digit != 0 {
+        break;
+      }
+      self.coefficient = BigInt::new(self.coefficient.negative, quotient);
+      self.scale -= 1;
+    }
+    self
+  }
+
+  /// the integer part, rounded towards zero
+  pub fn trunc(&self) -> BigInt {
+    let (quotient, _) = divrem_magnitude(&self.coefficient.magnitude, &pow10(self.scale).magnitude);
+    BigInt::new(self.coefficient.negative, quotient)
+  }
+
+  pub fn is_integral(&self) -> bool {
+    self.clone().reduce(0).scale == 0
+  }
+
+  /// the value as an i64 if it's a whole number that fits
+  pub fn to_i64(&self) -> Option<i64> {
+    if self.is_integral() {
+      self.trunc().to_i64()
+    } else {
+      None
+    }
+  }
+
+  /// the closest float
+  pub fn to_f64(&self) -> f64 {
+    self.to_string().parse().unwrap_or(f64::NAN)
+  }
+
+  /// the quotient rounded half to even at DIVISION_SCALE digits after the point, with trailing
+  /// zeros dropped down to the scale an exact quotient would have; none when dividing by zero
+  pub fn div(&self, divisor: &Self) -> Option<Self> {
+    if divisor.is_zero() {
+      return None;
+    }
+
+    let ideal = self.scale.saturating_sub(divisor.scale);
+    let scale = DIVISION_SCALE.max(ideal);
+    let numerator = &self.coefficient.abs() * &pow10(scale + divisor.scale);
+    let denominator = &divisor.coefficient.abs() * &pow10(self.scale);
+    let (mut quotient, remainder) = numerator.div_floor(&denominator)?;
+
+    let half = cmp_magnitude(&(&remainder + &remainder).magnitude, &denominator.magnitude);
+    if half == Ordering::Greater || (half == Ordering::Equal && quotient.is_odd()) {
+      quotient = &quotient + &BigInt::from(1);
+    }
+    if self.coefficient.negative != divisor.coefficient.negative {
+      quotient = -&quotient;
+    }
+
+    Some(
+      Self {
+        coefficient: quotient,
+        scale,
+      }
+      .reduce(ideal),
+    )
+  }
+
+  /// floored quotient and remainder like `~/` and `%`, none when dividing by zero
+  pub fn div_floor(&self, divisor: &Self) -> Option<(Self, Self)> {
+    let (a, b, scale) = self.align(divisor);
+    let (quotient, remainder) = a.div_floor(&b)?;
+    Some((
+      Self::from(quotient),
+      Self {
+        coefficient: remainder,
+        scale,
+      },
+    ))
+  }
+
+  /// none if the product would have more than MAX_SCALE digits after the point
+  pub fn checked_mul(&self, other: &Self) -> Option<Self> {
+    let scale = self.scale + other.scale;
+    if scale > MAX_SCALE {
+      return None;
+    }
+    Some(Self {
+      coefficient: &self.coefficient * &other.coefficient,
+      scale,
+    })
+  }
+
+  /// none if the result would have more than MAX_SCALE digits after the point or it divides by
+  /// zero
+  pub fn pow(&self, exponent: i64) -> Option<Self> {
+    if exponent < 0 {
+      return Self::from(BigInt::from(1)).div(&self.pow(exponent.checked_neg()?)?);
+    }
+
+    let exponent = u32::try_from(exponent).ok()?;
+    let scale = self
+      .scale
+      .checked_mul(exponent)
+      .filter(|&scale| scale <= MAX_SCALE)?;
+    Some(Self {
+      coefficient: self.coefficient.pow(exponent),
+      scale,
+    })
+  }
+}
+
+impl From<BigInt> for Decimal {
+  fn from(coefficient: BigInt) -> Self {
+    Self {
+      coefficient,
+      scale: 0,
+    }
+  }
+}
+
+impl Ord for Decimal {
+  fn cmp(&self, other: &Self) -> Ordering {
+    let (a, b, _) = self.align(other);
+    a.cmp(&b)
+  }
+}
+
+impl PartialOrd for Decimal {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl PartialEq for Decimal {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Decimal {}
+
+impl ops::Neg for &Decimal {
+  type Output = Decimal;
+
+  fn neg(self) -> Decimal {
+    Decimal {
+      coefficient: -&self.coefficient,
+      scale: self.scale,
+    }
+  }
+}
+
+impl ops::Add for &Decimal {
+  type Output = Decimal;
+
+  fn add(self, other: &Decimal) -> Decimal {
+    let (a, b, scale) = self.align(other);
+    Decimal {
+      coefficient: &a + &b,
+      scale,
+    }
+  }
+}
+
+impl ops::Sub for &Decimal {
+  type Output = Decimal;
+
+  fn sub(self, other: &Decimal) -> Decimal {
+    self + &-other
+  }
+}
+
+impl fmt::Display for Decimal {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let digits = self.coefficient.abs().to_string();
+    let scale = self.scale as usize;
+    // at least one digit before the point
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+
+    if self.coefficient.negative {
+      write!(f, "-")?;
+    }
+    if fraction.is_empty() {
+      write!(f, "{}", whole)
+    } else {
+      write!(f, "{}.{}", whole, fraction)
+    }
+  }
+}
+
+#[cfg(test)]
+mod bignum_tests {
+  use super::*;
+
+  fn big(text: &str) -> BigInt {
+    BigInt::parse(text, 10).unwrap()
+  }
+
+  fn dec(text: &str) -> Decimal {
+    Decimal::parse(text).unwrap()
+  }
+
+  #[test]
+  fn bigint_arithmetic() {
+    let a = big("123456789012345678901234567890");
+    let b = big("-987654321098765432109876543210");
+    assert_eq!((&a + &b).to_string(), "-864197532086419753208641975320");
+    assert_eq!((&a - &b).to_string(), "1111111110111111111011111111100");
+    assert_eq!(
+      (&a * &b).to_string(),
+      "-121932631137021795226185032733622923332237463801111263526900"
+    );
+    assert_eq!(
+      BigInt::from(2).pow(100).to_string(),
+      "1267650600228229401496703205376"
+    );
+    assert_eq!(BigInt::from(i64::MIN).to_string(), "-9223372036854775808");
+    assert_eq!(BigInt::from(i64::MIN).to_i64(), Some(i64::MIN));
+    assert_eq!(big("9223372036854775808").to_i64(), None);
+    assert_eq!(BigInt::parse("ff", 16).unwrap().to_string(), "255");
+    assert!(b < a);
+
+    let (q, r) = b.div_floor(&a).unwrap();
+    assert_eq!(
+      (q.to_string(), r.to_string()),
+      (
+        "-9".to_string(),
+        "123456780012345678001234567800".to_string()
+      )
+    );
+    let (q, r) = big("-7").div_floor(&big("2")).unwrap();
+    assert_eq!(
+      (q.to_string(), r.to_string()),
+      ("-4".to_string(), "1".to_string())
+    );
+    assert!(a.div_floor(&BigInt::default()).is_none());
+  }
+
+  #[test]
+  fn decimal_arithmetic() {
+    assert_eq!((&dec("0.1") + &dec("0.2")).to_string(), "0.3");
+    assert_eq!((&dec("1.10") - &dec("0.1")).to_string(), "1.00");
+    assert_eq!(
+      dec("1.5").checked_mul(&dec("-2.25")).unwrap().to_string(),
+      "-3.375"
+    );
+    let tiny = dec("1e-6000");
+    assert!(tiny.checked_mul(&tiny).is_none());
+    assert!(tiny.pow(2).is_none());
+    assert_eq!((&tiny + &dec("1")).to_string().len(), 6002);
+    assert!(Decimal::parse("1e-999999999").is_none());
+    assert!(Decimal::parse("1e999999999").is_none());
+    assert!(Decimal::parse("1e-9223372036854775808").is_none());
+    assert_eq!(
+      dec("1").div(&dec("3")).unwrap().to_string(),
+      "0.3333333333333333333333333333"
+    );
+    assert_eq!(dec("10").div(&dec("4")).unwrap().to_string(), "2.5");
+    assert_eq!(dec("6.00").div(&dec("2")).unwrap().to_string(), "3.00");
+    assert_eq!(
+      dec("2").div(&dec("3")).unwrap().to_string(),
+      "0.6666666666666666666666666667"
+    );
+    assert_eq!(dec("1.5e3").to_string(), "1500");
+    assert_eq!(dec("-.5").to_string(), "-0.5");
+    assert_eq!(dec("2").pow(-2).unwrap().to_string(), "0.25");
+    assert_eq!(dec("1.10"), dec("1.1"));
+    assert_eq!(Decimal::from_f64(0.5).unwrap(), dec("0.5"));
+    assert_ne!(Decimal::from_f64(0.1).unwrap(), dec("0.1"));
+
+    let (q, r) = dec("-7.5").div_floor(&dec("2")).unwrap();
+    assert_eq!(
+      (q.to_string(), r.to_string()),
+      ("-4".to_string(), "0.5".to_string())
+    );
+  }
+}
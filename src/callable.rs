@@ -2,42 +2,73 @@ use crate::{
   environment::Env,
   errors::RuntimeError,
   interpreter::Interpreter,
-  types::{Object, Stmt},
+  types::{Object, Param, Stmt, Token},
 };
-use std::{cell::RefCell, fmt, rc::Rc, time};
+use std::{cell::RefCell, fmt, rc::Rc};
 
-pub trait Callable: fmt::Display {
-  fn call(
-    &self,
-    interpreter: &mut Interpreter,
-    arguments: &[Object],
-  ) -> Result<Object, RuntimeError>;
-  fn arity(&self) -> usize;
+/// how many positional arguments a callable takes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Arity {
+  pub min: usize,
+  pub max: Option<usize>, // none when any number of extra arguments is gathered up
 }
 
-pub struct ClockFn;
+impl Arity {
+  pub const fn exactly(n: usize) -> Self {
+    Self {
+      min: n,
+      max: Some(n),
+    }
+  }
+
+  pub const fn at_least(n: usize) -> Self {
+    Self { min: n, max: None }
+  }
+
+  pub fn accepts(&self, count: usize) -> bool {
+    count >= self.min && self.max.is_none_or(|max| count <= max)
+  }
+}
 
-impl fmt::Display for ClockFn {
+impl fmt::Display for Arity {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "<fn> clock")
+    match self.max {
+      Some(max) if max == self.min => write!(f, "{}", max),
+      Some(max) => write!(f, "{} to {}", self.min, max),
+      None => write!(f, "at least {}", self.min),
+    }
   }
 }
 
-impl Callable for ClockFn {
+pub trait Callable: fmt::Display {
   fn call(
     &self,
-    _interpreter: &mut Interpreter,
-    _arguments: &[Object],
-  ) -> Result<Object, RuntimeError> {
-    let now = time::SystemTime::now()
-      .duration_since(time::UNIX_EPOCH)
-      .unwrap(); // TODO: no unwrap here
+    interpreter: &mut Interpreter,
+    arguments: &[Object],
+  ) -> Result<Object, RuntimeError>;
+  fn arity(&self) -> Arity;
 
-    Ok(Object::Number(now.as_secs_f64()))
+  /// fails on a named argument this callable has no parameter for. only functions written in lox
+  /// know what their parameters are called, anything else refuses named arguments
+  fn check_named(&self, named: &[(Token, Object)]) -> Result<(), RuntimeError> {
+    match named.first() {
+      Some((name, _)) => Err(RuntimeError::InvalidFunctionCall(
+        name.line,
+        name.lexeme.clone(),
+        format!("{} takes no named arguments", self),
+      )),
+      None => Ok(()),
+    }
   }
 
-  fn arity(&self) -> usize {
-    0
+  /// like call, with some of the arguments given by name once `check_named` has let them through
+  fn call_named(
+    &self,
+    interpreter: &mut Interpreter,
+    arguments: &[Object],
+    _named: &[(Token, Object)],
+  ) -> Result<Object, RuntimeError> {
+    self.call(interpreter, arguments)
   }
 }
 
@@ -54,6 +85,53 @@ impl LoxFunction {
       closure,
     }
   }
+
+  /// a fresh scope for one call with every parameter bound, missing arguments take their default
+  /// which is evaluated in that scope so it can refer to the parameters before it. the names of
+  /// named arguments have been checked by `check_named` already
+  fn bind(
+    &self,
+    interpreter: &mut Interpreter,
+    params: &[Param],
+    arguments: &[Object],
+    named: &[(Token, Object)],
+  ) -> Result<Rc<RefCell<Env>>, RuntimeError> {
+    let environment = Rc::new(RefCell::new(Env::new_enclosing(Rc::clone(&self.closure))));
+    let mut positional = arguments.iter();
+    for param in params {
+      let value = if param.rest {
+        let rest = Object::new_list(positional.by_ref().cloned().collect());
+        interpreter.track(&rest)?;
+        rest
+      } else {
+        let by_name = named
+          .iter()
+          .find(|(name, _)| name.lexeme == param.name.lexeme);
+        match (positional.next(), by_name, &param.default) {
+          (Some(_), Some((name, _)), _) => {
+            return Err(RuntimeError::InvalidFunctionCall(
+              name.line,
+              name.lexeme.clone(),
+              "argument given both by position and by name".to_string(),
+            ));
+          }
+          (Some(value), None, _) | (None, Some((_, value)), _) => value.clone(),
+          (None, None, Some(default)) => {
+            interpreter.evaluate_in(default, Rc::clone(&environment))?
+          }
+          (None, None, None) => {
+            return Err(RuntimeError::InvalidNumArgs(format!(
+              "missing argument '{}' of {}",
+              param.name.lexeme, self
+            )));
+          }
+        }
+      };
+      environment.borrow_mut().define(&param.name.lexeme, &value);
+    }
+
+    Ok(environment)
+  }
 }
 
 impl fmt::Display for LoxFunction {
@@ -72,19 +150,39 @@ impl Callable for LoxFunction {
     interpreter: &mut Interpreter,
     arguments: &[Object],
   ) -> Result<Object, RuntimeError> {
-    if let Stmt::Function {
-      name: _,
-      params,
-      body,
-    } = &self.declaration
-    {
-      let mut environment = Env::new_enclosing(Rc::clone(&self.closure));
-
-      for (param, arg) in params.iter().zip(arguments.iter()) {
-        environment.define(&param.lexeme, &arg);
-      }
+    self.call_named(interpreter, arguments, &[])
+  }
+
+  fn check_named(&self, named: &[(Token, Object)]) -> Result<(), RuntimeError> {
+    let Stmt::Function { params, .. } = &self.declaration else {
+      return Ok(());
+    };
+
+    let unknown = named.iter().find(|(name, _)| {
+      !params
+        .iter()
+        .any(|p| !p.rest && p.name.lexeme == name.lexeme)
+    });
+    match unknown {
+      Some((name, _)) => Err(RuntimeError::InvalidFunctionCall(
+        name.line,
+        name.lexeme.clone(),
+        format!("{} has no parameter named '{}'", self, name.lexeme),
+      )),
+      None => Ok(()),
+    }
+  }
 
-      match interpreter.execute_block(&body, Rc::new(RefCell::new(environment))) {
+  fn call_named(
+    &self,
+    interpreter: &mut Interpreter,
+    arguments: &[Object],
+    named: &[(Token, Object)],
+  ) -> Result<Object, RuntimeError> {
+    if let Stmt::Function { params, body, .. } = &self.declaration {
+      let environment = self.bind(interpreter, params, arguments, named)?;
+
+      match interpreter.execute_block(body, environment) {
         Ok(_) => {}
         Err(RuntimeError::ReturnCalled(val)) => {
           if let Some(val) = val {
@@ -104,11 +202,22 @@ impl Callable for LoxFunction {
     }
   }
 
-  fn arity(&self) -> usize {
+  fn arity(&self) -> Arity {
     if let Stmt::Function { params, .. } = &self.declaration {
-      params.len()
+      let required = params
+        .iter()
+        .filter(|p| !p.rest && p.default.is_none())
+        .count();
+      Arity {
+        min: required,
+        max: if params.iter().any(|p| p.rest) {
+          None
+        } else {
+          Some(params.len())
+        },
+      }
     } else {
-      0
+      Arity::exactly(0)
     }
   }
 }
@@ -1,27 +1,49 @@
 use crate::{
   errors::EnvError,
+  limits::Heap,
   types::{Object, Token},
 };
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+  cell::RefCell,
+  collections::{HashMap, HashSet},
+  rc::Rc,
+};
 
-#[derive(Clone, Default)]
+#[derive(Default)]
 pub struct Env {
   values: HashMap<String, Object>,
+  constants: HashSet<String>, // names declared with const, which can't be assigned or redeclared
   enclosing: Option<Rc<RefCell<Env>>>,
+  heap: Rc<Heap>,
 }
 
 impl Env {
-  pub fn new() -> Self {
+  pub fn new(heap: Rc<Heap>) -> Self {
     Self {
       values: HashMap::new(),
+      constants: HashSet::new(),
       enclosing: None,
+      heap,
     }
   }
 
   pub fn new_enclosing(enclosing: Rc<RefCell<Env>>) -> Self {
+    let heap = Rc::clone(&enclosing.borrow().heap);
     Self {
       values: HashMap::new(),
+      constants: HashSet::new(),
       enclosing: Some(enclosing),
+      heap,
+    }
+  }
+
+  /// inserts or overwrites a value, keeping the heap's byte count in step
+  fn set(&mut self, name: &str, value: &Object) {
+    let value = value.clone();
+    self.heap.alloc(name.len() + self.heap.size_of(&value));
+    if let Some(old) = self.values.insert(name.to_string(), value) {
+      self.heap.free(name.len() + self.heap.size_of(&old));
+      old.dismantle();
     }
   }
 
@@ -34,49 +56,93 @@ impl Env {
       return enclosing.borrow_mut().get(name);
     }
 
-    Err(EnvError::ValueNotFound(
-      name.line,
-      name.lexeme.clone(),
-      format!("no value found for var {}", name.lexeme.clone()),
-    ))
+    Err(Self::not_found(name))
   }
 
   pub fn define(&mut self, name: &str, value: &Object) {
-    self.values.insert(name.to_string(), value.clone());
+    self.set(name, value);
+  }
+
+  /// defines the variable of a var or const statement, failing if it would replace a constant
+  pub fn declare(&mut self, name: &Token, value: &Object, constant: bool) -> Result<(), EnvError> {
+    if self.constants.contains(&name.lexeme) {
+      return Err(EnvError::ConstantAssignment(
+        name.line,
+        name.lexeme.clone(),
+        "can't redeclare a constant".to_string(),
+      ));
+    }
+
+    if constant {
+      self.constants.insert(name.lexeme.clone());
+    }
+    self.set(&name.lexeme, value);
+    Ok(())
   }
 
   pub fn get_at(env: Rc<RefCell<Self>>, distance: i32, name: &str) -> Option<Object> {
-    Self::ancestor(env, distance)
+    Self::ancestor(env, distance)?
       .borrow()
       .values
       .get(name)
       .cloned()
   }
 
-  pub fn assign_at(env: Rc<RefCell<Self>>, distance: i32, name: &Token, value: &Object) {
+  pub fn assign_at(
+    env: Rc<RefCell<Self>>,
+    distance: i32,
+    name: &Token,
+    value: &Object,
+  ) -> Result<(), EnvError> {
     Self::ancestor(env, distance)
+      .ok_or_else(|| Self::not_found(name))?
       .borrow_mut()
-      .values
-      .insert(name.lexeme.to_string(), value.clone());
+      .set(&name.lexeme, value);
+    Ok(())
   }
 
-  fn ancestor(env: Rc<RefCell<Self>>, distance: i32) -> Rc<RefCell<Env>> {
+  /// none when there are fewer than `distance` envs around `env`
+  fn ancestor(env: Rc<RefCell<Self>>, distance: i32) -> Option<Rc<RefCell<Env>>> {
     // TODO: this is wrong because we don't want to clone but instead get a dependent copy
     let mut environment = env;
     for _ in 0..distance {
-      let next = environment
-        .borrow()
-        .enclosing
-        .clone()
-        .expect("No enclosing environment found at this distance");
+      let next = environment.borrow().enclosing.clone()?;
       environment = next;
     }
-    environment
+    Some(environment)
+  }
+
+  pub fn not_found(name: &Token) -> EnvError {
+    EnvError::ValueNotFound(
+      name.line,
+      name.lexeme.clone(),
+      format!("no value found for var {}", name.lexeme.clone()),
+    )
+  }
+
+  /// the outermost env `env` is nested in, the globals of the file it belongs to
+  pub fn root(env: Rc<RefCell<Self>>) -> Rc<RefCell<Env>> {
+    let mut environment = env;
+    loop {
+      let next = environment.borrow().enclosing.clone();
+      match next {
+        Some(next) => environment = next,
+        None => return environment,
+      }
+    }
   }
 
   pub fn assign(&mut self, name: &Token, value: &Object) -> Result<(), EnvError> {
+    if self.constants.contains(&name.lexeme) {
+      return Err(EnvError::ConstantAssignment(
+        name.line,
+        name.lexeme.clone(),
+        "can't assign to a constant".to_string(),
+      ));
+    }
+
     if self.values.contains_key(&name.lexeme) {
-      self.values.insert(name.lexeme.clone(), value.clone());
+      self.set(&name.lexeme, value);
       return Ok(());
     }
 
@@ -92,3 +158,12 @@ impl Env {
     ))
   }
 }
+
+impl Drop for Env {
+  fn drop(&mut self) {
+    for (name, value) in self.values.drain() {
+      self.heap.free(name.len() + self.heap.size_of(&value));
+      value.dismantle();
+    }
+  }
+}
@@ -1,5 +1,13 @@
-use crate::{error_indent, red_text, types::Object};
-use std::{fmt, io};
+use crate::{
+  error_indent,
+  limits::Limit,
+  red_text,
+  types::{LoxMap, Object},
+};
+use std::{
+  fmt,
+  io::{self, Write},
+};
 
 #[derive(Debug)]
 pub enum LoxError {
@@ -44,8 +52,9 @@ impl From<io::Error> for LoxError {
 }
 
 impl LoxError {
-  pub fn report(err: &LoxError) {
-    println!("{}", err);
+  pub fn report(err: &LoxError, out: &mut dyn Write) {
+    // a broken diagnostics sink has nowhere left to report to
+    let _ = writeln!(out, "{}", err);
   }
 }
 
@@ -54,16 +63,12 @@ impl LoxError {
 pub enum LexError {
   IncompleteString(usize, String, String),
   UnknownChar(usize, String, String),
-  ParseFloatError(std::num::ParseFloatError),
+  InvalidEscape(usize, String, String), // line, escape sequence, msg
+  IncompleteComment(usize, String, String),
+  InvalidNumber(usize, String, String), // line, literal, msg
   Eof,
 }
 
-impl From<std::num::ParseFloatError> for LexError {
-  fn from(err: std::num::ParseFloatError) -> LexError {
-    LexError::ParseFloatError(err)
-  }
-}
-
 impl fmt::Display for LexError {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     match self {
@@ -87,7 +92,36 @@ impl fmt::Display for LexError {
         lexeme,
         msg
       ),
-      LexError::ParseFloatError(e) => write!(f, "parse float error: {}", e),
+      LexError::InvalidEscape(line, sequence, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "LexError::InvalidEscape",
+        error_indent!(),
+        line + 1,
+        sequence,
+        msg
+      ),
+      LexError::IncompleteComment(line, lexeme, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "LexError::IncompleteComment",
+        error_indent!(),
+        line + 1,
+        lexeme,
+        msg
+      ),
+      LexError::InvalidNumber(line, literal, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "LexError::InvalidNumber",
+        error_indent!(),
+        line + 1,
+        literal,
+        msg
+      ),
       LexError::Eof => write!(f, "hit eof while lexing"),
     }
   }
@@ -166,7 +200,95 @@ pub enum RuntimeError {
   VariableUninitialized(usize, String, String),
   InvalidFunctionCall(usize, String, String),
   ReturnCalled(Option<Object>),
+  BreakCalled(Option<String>), // label of the loop to leave, innermost loop if none
+  ContinueCalled(Option<String>), // label of the loop to continue, innermost loop if none
   InvalidNumArgs(String),
+  Io(io::Error),
+  LimitExceeded(Limit, String),
+  NativeError(String, String),            // native name, msg
+  IndexOutOfBounds(usize, usize, String), // line, column, msg
+  KeyNotFound(usize, usize, String),      // line, column, msg
+  Thrown(usize, Object),                  // line of the throw, thrown value
+  ImportFailed(usize, String, String),    // line, imported path, msg
+  DivisionByZero(usize, String, String),
+  IntegerOverflow(usize, String, String),
+  ConstantAssignment(usize, String, String),
+  PermissionDenied(usize, String, String), // line, what was refused, msg
+  Exit(i32),                               // status the script exits with
+}
+
+impl RuntimeError {
+  /// the value a script's catch block receives for this error, none for what scripts can't catch:
+  /// control flow, exhausted limits and exit
+  pub fn to_object(&self) -> Option<Object> {
+    let (kind, line, message) = match self {
+      RuntimeError::Thrown(_, value) => return Some(value.clone()),
+      RuntimeError::ReturnCalled(_)
+      | RuntimeError::BreakCalled(_)
+      | RuntimeError::ContinueCalled(_)
+      | RuntimeError::LimitExceeded(..)
+      | RuntimeError::Exit(_) => return None,
+      RuntimeError::InvalidType(line, _, msg) => ("InvalidType", Some(*line), msg.clone()),
+      RuntimeError::NumberStringAddition(line, _, msg) => {
+        ("NumberStringAddition", Some(*line), msg.clone())
+      }
+      RuntimeError::ValueNotFound(line, _, msg) => ("ValueNotFound", Some(*line), msg.clone()),
+      RuntimeError::VariableUninitialized(line, _, msg) => {
+        ("VariableUninitialized", Some(*line), msg.clone())
+      }
+      RuntimeError::InvalidFunctionCall(line, _, msg) => {
+        ("InvalidFunctionCall", Some(*line), msg.clone())
+      }
+      RuntimeError::InvalidNumArgs(msg) => ("InvalidNumArgs", None, msg.clone()),
+      RuntimeError::Io(e) => ("Io", None, e.to_string()),
+      RuntimeError::NativeError(name, msg) => ("NativeError", None, format!("{}: {}", name, msg)),
+      RuntimeError::IndexOutOfBounds(line, _, msg) => {
+        ("IndexOutOfBounds", Some(*line), msg.clone())
+      }
+      RuntimeError::KeyNotFound(line, _, msg) => ("KeyNotFound", Some(*line), msg.clone()),
+      RuntimeError::ImportFailed(line, _, msg) => ("ImportFailed", Some(*line), msg.clone()),
+      RuntimeError::DivisionByZero(line, _, msg) => ("DivisionByZero", Some(*line), msg.clone()),
+      RuntimeError::IntegerOverflow(line, _, msg) => ("IntegerOverflow", Some(*line), msg.clone()),
+      RuntimeError::ConstantAssignment(line, _, msg) => {
+        ("ConstantAssignment", Some(*line), msg.clone())
+      }
+      RuntimeError::PermissionDenied(line, _, msg) => {
+        ("PermissionDenied", Some(*line), msg.clone())
+      }
+    };
+
+    let mut error = LoxMap::default();
+    error.insert(
+      Object::String("kind".to_string()),
+      Object::String(kind.to_string()),
+    );
+    error.insert(
+      Object::String("message".to_string()),
+      Object::String(message),
+    );
+    error.insert(
+      Object::String("line".to_string()),
+      line.map_or(Object::None, |l| Object::Int(l as i64 + 1)),
+    );
+    Some(Object::new_map(error))
+  }
+}
+
+impl From<io::Error> for RuntimeError {
+  fn from(err: io::Error) -> RuntimeError {
+    RuntimeError::Io(err)
+  }
+}
+
+impl From<EnvError> for RuntimeError {
+  fn from(err: EnvError) -> RuntimeError {
+    match err {
+      EnvError::ValueNotFound(line, lexeme, msg) => RuntimeError::ValueNotFound(line, lexeme, msg),
+      EnvError::ConstantAssignment(line, lexeme, msg) => {
+        RuntimeError::ConstantAssignment(line, lexeme, msg)
+      }
+    }
+  }
 }
 
 impl fmt::Debug for RuntimeError {
@@ -215,9 +337,75 @@ impl fmt::Debug for RuntimeError {
         Some(obj) => write!(f, "Return called with value: {}", obj),
         None => write!(f, "Return called with no value"),
       },
+      RuntimeError::BreakCalled(label) => write!(f, "Break called with label: {:?}", label),
+      RuntimeError::ContinueCalled(label) => write!(f, "Continue called with label: {:?}", label),
       RuntimeError::InvalidNumArgs(msg) => {
         write!(f, "Invalid number of arguments: {}", msg)
       }
+      RuntimeError::Io(e) => write!(f, "Io error: {}", e),
+      RuntimeError::LimitExceeded(limit, msg) => write!(f, "Exceeded {}: {}", limit, msg),
+      RuntimeError::Exit(code) => write!(f, "Exit called with status {}", code),
+      RuntimeError::NativeError(name, msg) => write!(f, "Error in native '{}': {}", name, msg),
+      RuntimeError::IndexOutOfBounds(line, column, msg) => {
+        write!(
+          f,
+          "[line {}:{}] Index out of bounds ({})",
+          line + 1,
+          column + 1,
+          msg
+        )
+      }
+      RuntimeError::KeyNotFound(line, column, msg) => {
+        write!(
+          f,
+          "[line {}:{}] Key not found ({})",
+          line + 1,
+          column + 1,
+          msg
+        )
+      }
+      RuntimeError::Thrown(line, value) => {
+        write!(f, "[line {}] Uncaught {}", line + 1, value.repr())
+      }
+      RuntimeError::ImportFailed(line, path, msg) => {
+        write!(f, "[line {}] Import of {} failed ({})", line + 1, path, msg)
+      }
+      RuntimeError::DivisionByZero(line, lexeme, msg) => {
+        write!(
+          f,
+          "[line {}] Division by zero in '{}' ({})",
+          line + 1,
+          lexeme,
+          msg
+        )
+      }
+      RuntimeError::IntegerOverflow(line, lexeme, msg) => {
+        write!(
+          f,
+          "[line {}] Integer overflow in '{}' ({})",
+          line + 1,
+          lexeme,
+          msg
+        )
+      }
+      RuntimeError::ConstantAssignment(line, lexeme, msg) => {
+        write!(
+          f,
+          "[line {}] Constant assignment to '{}' ({})",
+          line + 1,
+          lexeme,
+          msg
+        )
+      }
+      RuntimeError::PermissionDenied(line, lexeme, msg) => {
+        write!(
+          f,
+          "[line {}] Permission denied for {} ({})",
+          line + 1,
+          lexeme,
+          msg
+        )
+      }
     }
   }
 }
@@ -283,6 +471,14 @@ impl fmt::Display for RuntimeError {
         error_indent!(),
         if let Some(o) = obj { o } else { &Object::None }
       ),
+      RuntimeError::BreakCalled(label) | RuntimeError::ContinueCalled(label) => write!(
+        f,
+        "{}: {}\n{}[Error]: {}",
+        red_text!("error"),
+        "RuntimeError::LoopControl",
+        error_indent!(),
+        label.as_deref().unwrap_or("no label")
+      ),
       RuntimeError::InvalidNumArgs(msg) => write!(
         f,
         "{}: {}\n{}[Error]: {}",
@@ -291,6 +487,119 @@ impl fmt::Display for RuntimeError {
         error_indent!(),
         msg
       ),
+      RuntimeError::Io(e) => write!(
+        f,
+        "{}: {}\n{}[Error]: {}",
+        red_text!("error"),
+        "RuntimeError::Io",
+        error_indent!(),
+        e
+      ),
+      RuntimeError::LimitExceeded(limit, msg) => write!(
+        f,
+        "{}: {}\n{}[Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::LimitExceeded",
+        error_indent!(),
+        limit,
+        msg
+      ),
+      RuntimeError::Exit(code) => write!(
+        f,
+        "{}: {}\n{}[Error]: exit called with status {}",
+        red_text!("error"),
+        "RuntimeError::Exit",
+        error_indent!(),
+        code
+      ),
+      RuntimeError::NativeError(name, msg) => write!(
+        f,
+        "{}: {}\n{}[Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::NativeError",
+        error_indent!(),
+        name,
+        msg
+      ),
+      RuntimeError::IndexOutOfBounds(line, column, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Column {} Error]: {}",
+        red_text!("error"),
+        "RuntimeError::IndexOutOfBounds",
+        error_indent!(),
+        line + 1,
+        column + 1,
+        msg
+      ),
+      RuntimeError::KeyNotFound(line, column, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Column {} Error]: {}",
+        red_text!("error"),
+        "RuntimeError::KeyNotFound",
+        error_indent!(),
+        line + 1,
+        column + 1,
+        msg
+      ),
+      RuntimeError::Thrown(line, value) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in 'throw']: uncaught {}",
+        red_text!("error"),
+        "RuntimeError::Thrown",
+        error_indent!(),
+        line + 1,
+        value.repr()
+      ),
+      RuntimeError::ImportFailed(line, path, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::ImportFailed",
+        error_indent!(),
+        line + 1,
+        path,
+        msg
+      ),
+      RuntimeError::DivisionByZero(line, lexeme, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::DivisionByZero",
+        error_indent!(),
+        line + 1,
+        lexeme,
+        msg
+      ),
+      RuntimeError::IntegerOverflow(line, lexeme, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::IntegerOverflow",
+        error_indent!(),
+        line + 1,
+        lexeme,
+        msg
+      ),
+      RuntimeError::ConstantAssignment(line, lexeme, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::ConstantAssignment",
+        error_indent!(),
+        line + 1,
+        lexeme,
+        msg
+      ),
+      RuntimeError::PermissionDenied(line, lexeme, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "RuntimeError::PermissionDenied",
+        error_indent!(),
+        line + 1,
+        lexeme,
+        msg
+      ),
     }
   }
 }
@@ -298,6 +607,7 @@ impl fmt::Display for RuntimeError {
 #[derive(Debug)]
 pub enum EnvError {
   ValueNotFound(usize, String, String),
+  ConstantAssignment(usize, String, String),
 }
 
 impl fmt::Display for EnvError {
@@ -313,6 +623,16 @@ impl fmt::Display for EnvError {
         lexeme,
         msg
       ),
+      EnvError::ConstantAssignment(line, lexeme, msg) => write!(
+        f,
+        "{}: {}\n{}[Line {} Error in '{}']: {}",
+        red_text!("error"),
+        "EnvError::ConstantAssignment",
+        error_indent!(),
+        line + 1,
+        lexeme,
+        msg
+      ),
     }
   }
 }
@@ -1,48 +1,151 @@
 use crate::{
-  callable::{ClockFn, LoxFunction},
+  callable::{Callable, LoxFunction},
   environment::Env,
   errors::{LoxError, RuntimeError},
-  types::{Expr, ExprVisitor, Object, Stmt, StmtVisitor, Token, TokenType},
+  lexer::Lexer,
+  limits::{Budget, Limits},
+  modules::{Module, ModuleLoader},
+  natives::{self, Capabilities},
+  parser::Parser,
+  resolver::Resolver,
+  types::{Expr, ExprVisitor, LoxMap, Object, Param, Stmt, StmtVisitor, Token, TokenType},
 };
 
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  fmt,
+  io::{BufRead, Write},
+  rc::Rc,
+};
+
+/// the evaluated arguments of a call, positional ones then named ones
+type Arguments = (Vec<Object>, Vec<(Token, Object)>);
 
 pub struct Interpreter {
   pub globals: Rc<RefCell<Env>>,
   env: Rc<RefCell<Env>>,
   locals: HashMap<Expr, i32>, // aka: side table
+  budget: Budget,
+  capabilities: Capabilities,
+  modules: ModuleLoader,
+  strict_division: bool, // dividing by zero raises instead of giving inf or NaN
+  wrapping_ints: bool,   // int overflow wraps around instead of raising
+
+  output: Box<dyn Write>,      // program output (print, repl echo)
+  diagnostics: Box<dyn Write>, // error reports
+  input: Box<dyn BufRead>,
 }
 
 impl Interpreter {
-  pub fn new() -> Self {
-    let globals = Rc::new(RefCell::new(Env::new()));
+  pub fn new(
+    capabilities: Capabilities,
+    output: Box<dyn Write>,
+    diagnostics: Box<dyn Write>,
+    input: Box<dyn BufRead>,
+  ) -> Self {
+    let budget = Budget::default();
+    let globals = Rc::new(RefCell::new(Env::new(budget.heap())));
 
-    globals
-      .borrow_mut()
-      .define("clock", &Object::Callable(Rc::new(ClockFn)));
+    natives::define_natives(&mut globals.borrow_mut(), capabilities);
 
     Self {
       globals: globals.clone(),
       env: globals,
       locals: HashMap::new(),
+      budget,
+      capabilities,
+      modules: ModuleLoader::default(),
+      strict_division: false,
+      wrapping_ints: false,
+      output,
+      diagnostics,
+      input,
     }
   }
 
+  pub fn output(&mut self) -> &mut dyn Write {
+    &mut self.output
+  }
+
+  pub fn input(&mut self) -> &mut dyn BufRead {
+    &mut self.input
+  }
+
+  /// limits apply to every following `interpret` run
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.budget.set_limits(limits);
+  }
+
+  pub fn set_strict_division(&mut self, strict: bool) {
+    self.strict_division = strict;
+  }
+
+  pub fn set_wrapping_ints(&mut self, wrapping: bool) {
+    self.wrapping_ints = wrapping;
+  }
+
+  /// fails if holding `bytes` more would go over the memory limit
+  pub fn reserve(&self, bytes: usize) -> Result<(), RuntimeError> {
+    self.budget.reserve(bytes)
+  }
+
+  /// charges the memory limit for a list or map that was just made
+  pub fn track(&self, collection: &Object) -> Result<(), RuntimeError> {
+    self.budget.track(collection)
+  }
+
+  /// charges the memory limit for `value` going into `collection`
+  pub fn hold(&self, collection: &Object, value: &Object) -> Result<(), RuntimeError> {
+    self.budget.hold(collection, value)
+  }
+
+  /// hands back what `hold` charged once `value` is taken out of `collection` again
+  pub fn release(&self, collection: &Object, value: &Object) {
+    self.budget.release(collection, value)
+  }
+
+  pub fn modules(&mut self) -> &mut ModuleLoader {
+    &mut self.modules
+  }
+
+  pub fn report(&mut self, err: &LoxError) {
+    LoxError::report(err, &mut self.diagnostics);
+  }
+
   // TODO: an expression alone in a lox file should cause an error or at least a warning
-  pub fn interpret(&mut self, statements: Vec<Stmt>, repl: bool) {
+  /// runs `statements`, reporting errors as it goes. only fails when the script calls exit
+  pub fn interpret(&mut self, statements: &[Stmt], repl: bool) -> Result<(), RuntimeError> {
+    self.budget.start();
+    let mut status = Ok(());
+
     for stmt in statements {
-      match stmt.accept(self) {
-        Ok(_) if repl => match stmt {
-          Stmt::Expression { expression } => match expression.accept(self) {
-            Ok(val) => println!("{}", val),
-            Err(e) => LoxError::report(&LoxError::RuntimeError(e)),
-          },
-          _ => {}
-        },
+      let result = match stmt {
+        // in the repl a bare expression echoes its value, evaluated only once
+        Stmt::Expression { expression } if repl => expression
+          .accept(self)
+          .and_then(|val| Ok(writeln!(self.output, "{}", val)?)),
+        _ => self.execute(stmt),
+      };
+
+      match result {
         Ok(_) => {}
-        Err(e) => LoxError::report(&LoxError::RuntimeError(e)),
+        // exit stops the run and is handed on, it's the host's to turn into a process status
+        Err(e @ RuntimeError::Exit(_)) => {
+          status = Err(e);
+          break;
+        }
+        // an exhausted budget stops the whole run, not just the current statement
+        Err(e @ RuntimeError::LimitExceeded(..)) => {
+          self.report(&LoxError::RuntimeError(e));
+          break;
+        }
+        Err(e) => self.report(&LoxError::RuntimeError(e)),
       }
     }
+
+    let _ = self.output.flush();
+    status
   }
 
   pub fn resolve(&mut self, expression: &Expr, depth: usize) {
@@ -51,7 +154,7 @@ impl Interpreter {
 
   fn check_num_operand(operand: &Object, operator: &Token) -> Result<(), RuntimeError> {
     match operand {
-      Object::Number(_) => Ok(()),
+      Object::Int(_) | Object::Number(_) => Ok(()),
       _ => Err(RuntimeError::InvalidType(
         operator.line,
         operator.lexeme.clone(),
@@ -65,8 +168,8 @@ impl Interpreter {
     right: &Object,
     operator: &Token,
   ) -> Result<(), RuntimeError> {
-    match (left, right) {
-      (Object::Number(_), Object::Number(_)) => Ok(()),
+    match (left.is_num(), right.is_num()) {
+      (true, true) => Ok(()),
       _ => Err(RuntimeError::InvalidType(
         operator.line,
         operator.lexeme.clone(),
@@ -75,6 +178,41 @@ impl Interpreter {
     }
   }
 
+  fn check_index(len: usize, index: &Object, bracket: &Token) -> Result<usize, RuntimeError> {
+    let Some(n) = index.to_int() else {
+      return Err(RuntimeError::InvalidType(
+        bracket.line,
+        bracket.lexeme.clone(),
+        format!("index must be an integer, got {}", index.repr()),
+      ));
+    };
+
+    normalize_index(n, len).ok_or_else(|| {
+      RuntimeError::IndexOutOfBounds(
+        bracket.line,
+        bracket.column,
+        format!("index {} is out of bounds for length {}", n, len),
+      )
+    })
+  }
+
+  fn check_key(key: &Object, token: &Token) -> Result<(), RuntimeError> {
+    if key.is_hashable() {
+      Ok(())
+    } else {
+      Err(RuntimeError::InvalidType(
+        token.line,
+        token.lexeme.clone(),
+        format!("{} can't be used as a map key", key.repr()),
+      ))
+    }
+  }
+
+  fn execute(&mut self, stmt: &Stmt) -> Result<(), RuntimeError> {
+    self.budget.step()?;
+    stmt.accept(self)
+  }
+
   pub fn execute_block(
     &mut self,
     statements: &[Stmt],
@@ -84,7 +222,7 @@ impl Interpreter {
     self.env = env.clone();
     let result = (|| {
       for stmt in statements {
-        stmt.accept(self)?;
+        self.execute(stmt)?;
       }
       Ok(())
     })();
@@ -93,44 +231,120 @@ impl Interpreter {
     result
   }
 
-  fn look_up_variable(&self, name: &Token, expr: &Expr) -> Result<Object, RuntimeError> {
-    match self.locals.get(expr) {
-      Some(distance) => Ok(Env::get_at(Rc::clone(&self.env), *distance, &name.lexeme).unwrap()),
-      None => Ok(self.globals.borrow_mut().get(name).unwrap()),
-    }
+  /// evaluates `expression` with `env` as the current scope
+  pub fn evaluate_in(
+    &mut self,
+    expression: &Expr,
+    env: Rc<RefCell<Env>>,
+  ) -> Result<Object, RuntimeError> {
+    let previous = std::mem::replace(&mut self.env, env);
+    let result = expression.accept(self);
+    self.env = previous;
+
+    result
   }
-}
 
-impl ExprVisitor<Result<Object, RuntimeError>> for Interpreter {
-  fn visit_literal_expr(&mut self, value: &Object) -> Result<Object, RuntimeError> {
-    Ok(value.clone())
+  /// calls a callable object the same way a call expression in a script would
+  pub fn call(&mut self, callee: &Object, arguments: &[Object]) -> Result<Object, RuntimeError> {
+    self.call_with_named(callee, arguments, &[])
   }
 
-  fn visit_grouping_expr(&mut self, expression: &Expr) -> Result<Object, RuntimeError> {
-    expression.accept(self)
+  /// like call, named arguments count towards the arity as much as positional ones
+  pub fn call_with_named(
+    &mut self,
+    callee: &Object,
+    arguments: &[Object],
+    named: &[(Token, Object)],
+  ) -> Result<Object, RuntimeError> {
+    let function = callee.as_callable()?; // this contains the runtime type check
+    Self::check_arguments(&*function, arguments, named)?;
+
+    self.budget.enter_call()?;
+    let result = function.call_named(self, arguments, named);
+    self.budget.exit_call();
+
+    result
   }
 
-  fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<Object, RuntimeError> {
-    let right = right.accept(self)?;
-    match operator.token_type {
-      TokenType::Minus => match Self::check_num_operand(&right, operator) {
-        Ok(_) => Ok(Object::Number(-right.to_num().unwrap())),
-        Err(e) => Err(e),
-      },
-      TokenType::Bang => Ok(Object::Bool(!right.to_bool())),
-      _ => Ok(Object::None),
+  /// fails if `function` can't take these arguments. the names are checked before the count so a
+  /// misspelled name isn't reported as an argument too many. kept out of `call_with_named`, which
+  /// stays on the stack through every nested call
+  fn check_arguments(
+    function: &dyn Callable,
+    arguments: &[Object],
+    named: &[(Token, Object)],
+  ) -> Result<(), RuntimeError> {
+    function.check_named(named)?;
+
+    let given = arguments.len() + named.len();
+    if !function.arity().accepts(given) {
+      return Err(RuntimeError::InvalidNumArgs(format!(
+        "expected {} arguments, but got {}",
+        function.arity(),
+        given
+      )));
     }
+
+    Ok(())
   }
 
-  fn visit_binary_expr(
+  /// evaluates the arguments of a call in order, positional ones first
+  fn evaluate_arguments(
     &mut self,
-    left: &Expr,
+    arguments: &[Expr],
+    named: &[(Token, Expr)],
+  ) -> Result<Arguments, RuntimeError> {
+    let mut ret_arguments = Vec::with_capacity(arguments.len());
+    for arg in arguments {
+      ret_arguments.push(arg.accept(self)?);
+    }
+
+    let mut named_arguments = Vec::with_capacity(named.len());
+    for (name, arg) in named {
+      named_arguments.push((name.clone(), arg.accept(self)?));
+    }
+
+    Ok((ret_arguments, named_arguments))
+  }
+
+  /// settles a break or continue aimed at the loop with `label`, anything else propagates;
+  /// returns whether that loop should go on
+  fn keep_looping(
+    result: Result<(), RuntimeError>,
+    label: &Option<Token>,
+  ) -> Result<bool, RuntimeError> {
+    let aimed_here = |target: &Option<String>| match target {
+      None => true,
+      Some(target) => label.as_ref().is_some_and(|l| &l.lexeme == target),
+    };
+
+    match result {
+      Ok(()) => Ok(true),
+      Err(RuntimeError::BreakCalled(target)) if aimed_here(&target) => Ok(false),
+      Err(RuntimeError::ContinueCalled(target)) if aimed_here(&target) => Ok(true),
+      Err(e) => Err(e),
+    }
+  }
+
+  /// runs `body` once with `name` bound to `value` in a fresh scope
+  fn execute_iteration(
+    &mut self,
+    name: &Token,
+    value: &Object,
+    body: &Stmt,
+  ) -> Result<(), RuntimeError> {
+    let mut env = Env::new_enclosing(self.env.clone());
+    env.define(&name.lexeme, value);
+    self.execute_block(std::slice::from_ref(body), Rc::new(RefCell::new(env)))
+  }
+
+  /// applies a binary arithmetic or comparison operator to already evaluated operands
+  fn binary_op(
+    &self,
+    left: Object,
     operator: &Token,
-    right: &Expr,
+    right: Object,
   ) -> Result<Object, RuntimeError> {
-    let left = left.accept(self)?;
-    let right = right.accept(self)?;
-
     match operator.token_type {
       TokenType::Greater => Ok(Object::Bool(left > right)),
       TokenType::GreaterEqual => Ok(Object::Bool(left >= right)),
@@ -140,21 +354,14 @@ impl ExprVisitor<Result<Object, RuntimeError>> for Interpreter {
       TokenType::BangEqual => Ok(Object::Bool(left != right)),
       TokenType::EqualEqual => Ok(Object::Bool(left == right)),
 
-      TokenType::Minus => {
-        Self::check_num_operands(&left, &right, operator)?;
-        Ok(Object::Number(
-          left.to_num().unwrap() - right.to_num().unwrap(),
-        ))
+      TokenType::Plus if left.is_str() && right.is_str() => {
+        let (left, right) = (left.to_str().unwrap(), right.to_str().unwrap());
+        self.budget.reserve(left.len() + right.len())?;
+        Ok(Object::String(left + &right))
       }
       TokenType::Plus => {
-        if left.is_str() && right.is_str() {
-          Ok(Object::String(
-            left.to_str().unwrap() + &right.to_str().unwrap(),
-          ))
-        } else if left.is_num() && right.is_num() {
-          Ok(Object::Number(
-            left.to_num().unwrap() + right.to_num().unwrap(),
-          ))
+        if left.is_num() && right.is_num() {
+          self.arithmetic(&left, operator, &right)
         } else {
           Err(RuntimeError::NumberStringAddition(
             0,
@@ -164,35 +371,545 @@ impl ExprVisitor<Result<Object, RuntimeError>> for Interpreter {
         }
       }
 
-      TokenType::Slash => {
-        Self::check_num_operands(&left, &right, operator)?;
-        Ok(Object::Number(
-          left.to_num().unwrap() / right.to_num().unwrap(),
-        ))
+      TokenType::Minus
+      | TokenType::Slash
+      | TokenType::TildeSlash
+      | TokenType::Percent
+      | TokenType::StarStar
+      | TokenType::Star => self.arithmetic(&left, operator, &right),
+
+      TokenType::Ampersand
+      | TokenType::Pipe
+      | TokenType::Caret
+      | TokenType::LessLess
+      | TokenType::GreaterGreater => self.bitwise(&left, operator, &right),
+
+      _ => Ok(Object::None),
+    }
+  }
+
+  /// `+ - * / ~/ % **` on numbers. two ints give an int, bigints and decimals take over from ints,
+  /// and anything with a float gives a float. `/` is true division, even two ints give a float and
+  /// a bigint gives a decimal
+  fn arithmetic(
+    &self,
+    left: &Object,
+    operator: &Token,
+    right: &Object,
+  ) -> Result<Object, RuntimeError> {
+    Self::check_num_operands(left, right, operator)?;
+    let slash = operator.token_type == TokenType::Slash;
+    match (left, right) {
+      (Object::Decimal(_), _) | (_, Object::Decimal(_)) => {
+        return self.decimal_arithmetic(left, operator, right);
+      }
+      (Object::Number(_), _) | (_, Object::Number(_)) => {}
+      (Object::Int(left), Object::Int(right)) if !slash => {
+        return self.int_arithmetic(*left, operator, *right);
+      }
+      (Object::Int(_), Object::Int(_)) => {}
+      _ if !slash => return self.bigint_arithmetic(left, operator, right),
+      // a float quotient would lose a bigint's digits, or be inf once it's past f64::MAX
+      _ => return self.decimal_arithmetic(left, operator, right),
+    }
+
+    if let TokenType::Slash | TokenType::TildeSlash | TokenType::Percent = operator.token_type {
+      let (left, right) = self.division_operands(left, right, operator)?;
+      return Ok(Object::Number(match operator.token_type {
+        TokenType::Slash => left / right,
+        TokenType::TildeSlash => (left / right).floor(),
+        // floored like ~/, the result takes the sign of the divisor so a == (a ~/ b) * b + a % b
+        _ => left - right * (left / right).floor(),
+      }));
+    }
+
+    let (left, right) = (left.to_num().unwrap(), right.to_num().unwrap());
+    Ok(Object::Number(match operator.token_type {
+      TokenType::Plus => left + right,
+      TokenType::Minus => left - right,
+      TokenType::Star => left * right,
+      _ => left.powf(right),
+    }))
+  }
+
+  fn int_arithmetic(
+    &self,
+    left: i64,
+    operator: &Token,
+    right: i64,
+  ) -> Result<Object, RuntimeError> {
+    // each operator gives the exact result, if it fits, and the wrapped around one
+    let (exact, wrapped) = match operator.token_type {
+      TokenType::Plus => (left.checked_add(right), left.wrapping_add(right)),
+      TokenType::Minus => (left.checked_sub(right), left.wrapping_sub(right)),
+      TokenType::Star => (left.checked_mul(right), left.wrapping_mul(right)),
+      // an int can't be inf or NaN, so these raise whether or not division is strict
+      TokenType::TildeSlash | TokenType::Percent if right == 0 => {
+        return Err(Self::division_by_zero(&left, operator));
+      }
+      TokenType::TildeSlash => {
+        let (quotient, remainder) = (left.wrapping_div(right), left.wrapping_rem(right));
+        let floored = if remainder != 0 && (remainder < 0) != (right < 0) {
+          quotient - 1
+        } else {
+          quotient
+        };
+        (left.checked_div(right).map(|_| floored), floored)
+      }
+      TokenType::Percent => {
+        let remainder = left.wrapping_rem(right);
+        let floored = if remainder != 0 && (remainder < 0) != (right < 0) {
+          remainder + right
+        } else {
+          remainder
+        };
+        (Some(floored), floored)
+      }
+      // like with floats, a negative power is a fraction
+      TokenType::StarStar if right < 0 => {
+        return Ok(Object::Number((left as f64).powf(right as f64)));
+      }
+      _ => {
+        let exponent = u32::try_from(right).unwrap_or(u32::MAX);
+        (left.checked_pow(exponent), left.wrapping_pow(exponent))
+      }
+    };
+
+    self.int_result(
+      exact,
+      wrapped,
+      || format!("{} {} {}", left, operator.lexeme, right),
+      operator,
+    )
+  }
+
+  /// arithmetic on a bigint and an int or another bigint, gives a bigint
+  fn bigint_arithmetic(
+    &self,
+    left: &Object,
+    operator: &Token,
+    right: &Object,
+  ) -> Result<Object, RuntimeError> {
+    let (left, right) = (left.to_bigint().unwrap(), right.to_bigint().unwrap());
+    let value = match operator.token_type {
+      TokenType::Plus => &left + &right,
+      TokenType::Minus => &left - &right,
+      TokenType::Star => &left * &right,
+      TokenType::TildeSlash | TokenType::Percent => {
+        let (quotient, remainder) = left
+          .div_floor(&right)
+          .ok_or_else(|| Self::division_by_zero(&left, operator))?;
+        if operator.token_type == TokenType::TildeSlash {
+          quotient
+        } else {
+          remainder
+        }
+      }
+      // like with ints, a negative power is a fraction
+      _ if right.is_negative() => {
+        return Ok(Object::Number(left.to_f64().powf(right.to_f64())));
+      }
+      _ => {
+        let exponent = right.to_i64().unwrap_or(i64::MAX);
+        self.reserve_power(left.bits(), exponent, operator)?;
+        left.pow(exponent as u32)
+      }
+    };
+
+    Ok(Object::BigInt(Rc::new(value)))
+  }
+
+  /// arithmetic on a decimal and an int, bigint or another decimal, gives a decimal. floats have to
+  /// be converted explicitly, mixing in their binary rounding is what decimals are there to avoid
+  fn decimal_arithmetic(
+    &self,
+    left: &Object,
+    operator: &Token,
+    right: &Object,
+  ) -> Result<Object, RuntimeError> {
+    let (Some(left), Some(right)) = (left.to_decimal(), right.to_decimal()) else {
+      return Err(RuntimeError::InvalidType(
+        operator.line,
+        operator.lexeme.clone(),
+        "can't mix decimals and floats, convert the float with decimal()".to_string(),
+      ));
+    };
+
+    let division_by_zero = || Self::division_by_zero(&left, operator);
+    let value = match operator.token_type {
+      TokenType::Plus => &left + &right,
+      TokenType::Minus => &left - &right,
+      TokenType::Star => left.checked_mul(&right).ok_or_else(|| {
+        RuntimeError::IntegerOverflow(
+          operator.line,
+          operator.lexeme.clone(),
+          "the product has too many digits after the point".to_string(),
+        )
+      })?,
+      TokenType::Slash => left.div(&right).ok_or_else(division_by_zero)?,
+      TokenType::TildeSlash => left.div_floor(&right).ok_or_else(division_by_zero)?.0,
+      TokenType::Percent => left.div_floor(&right).ok_or_else(division_by_zero)?.1,
+      _ => {
+        let exponent = right.to_i64().ok_or_else(|| {
+          RuntimeError::InvalidType(
+            operator.line,
+            operator.lexeme.clone(),
+            format!(
+              "a decimal can only be raised to a whole power, not {}",
+              right
+            ),
+          )
+        })?;
+        if left.is_zero() && exponent < 0 {
+          return Err(division_by_zero());
+        }
+        self.reserve_power(left.bits(), exponent.saturating_abs(), operator)?;
+        left.pow(exponent).ok_or_else(|| {
+          RuntimeError::IntegerOverflow(
+            operator.line,
+            operator.lexeme.clone(),
+            format!("{} ** {} has too many digits", left, exponent),
+          )
+        })?
+      }
+    };
+
+    Ok(Object::Decimal(Rc::new(value)))
+  }
+
+  /// fails before raising a `bits` long number to `exponent` if the result couldn't be held
+  fn reserve_power(&self, bits: u64, exponent: i64, operator: &Token) -> Result<(), RuntimeError> {
+    if exponent > u32::MAX as i64 {
+      return Err(RuntimeError::IntegerOverflow(
+        operator.line,
+        operator.lexeme.clone(),
+        format!("the exponent {} is too large", exponent),
+      ));
+    }
+    self
+      .budget
+      .reserve((bits as usize).saturating_mul(exponent as usize) / 8)
+  }
+
+  fn division_by_zero(left: &dyn fmt::Display, operator: &Token) -> RuntimeError {
+    RuntimeError::DivisionByZero(
+      operator.line,
+      operator.lexeme.clone(),
+      format!("{} {} 0", left, operator.lexeme),
+    )
+  }
+
+  /// `& | ^ << >>`, which only work on ints
+  fn bitwise(
+    &self,
+    left: &Object,
+    operator: &Token,
+    right: &Object,
+  ) -> Result<Object, RuntimeError> {
+    let (Object::Int(left), Object::Int(right)) = (left, right) else {
+      return Err(RuntimeError::InvalidType(
+        operator.line,
+        operator.lexeme.clone(),
+        "bitwise operators only take 64-bit ints".to_string(),
+      ));
+    };
+    let (left, right) = (*left, *right);
+
+    let shift = match operator.token_type {
+      TokenType::LessLess | TokenType::GreaterGreater if right < 0 => {
+        return Err(RuntimeError::InvalidType(
+          operator.line,
+          operator.lexeme.clone(),
+          format!("can't shift by a negative amount, got {}", right),
+        ));
+      }
+      _ => u32::try_from(right).unwrap_or(u32::MAX),
+    };
+
+    match operator.token_type {
+      TokenType::Ampersand => Ok(Object::Int(left & right)),
+      TokenType::Pipe => Ok(Object::Int(left | right)),
+      TokenType::Caret => Ok(Object::Int(left ^ right)),
+      // arithmetic shift, shifting everything out leaves the sign
+      TokenType::GreaterGreater => Ok(Object::Int(left >> shift.min(63))),
+      _ => {
+        // bits shifted out of the top are an overflow
+        let shifted = left.checked_shl(shift).unwrap_or(0);
+        let exact = Some(shifted).filter(|s| shift < 64 && s >> shift == left);
+        self.int_result(
+          exact,
+          shifted,
+          || format!("{} << {}", left, right),
+          operator,
+        )
+      }
+    }
+  }
+
+  /// an int operation's result, the wrapped around one only if ints wrap
+  fn int_result(
+    &self,
+    exact: Option<i64>,
+    wrapped: i64,
+    operation: impl FnOnce() -> String,
+    operator: &Token,
+  ) -> Result<Object, RuntimeError> {
+    match exact {
+      Some(n) => Ok(Object::Int(n)),
+      None if self.wrapping_ints => Ok(Object::Int(wrapped)),
+      None => Err(RuntimeError::IntegerOverflow(
+        operator.line,
+        operator.lexeme.clone(),
+        format!("{} overflows a 64-bit integer", operation()),
+      )),
+    }
+  }
+
+  /// both operands of a division, which in strict mode can't divide by zero instead of giving
+  /// inf or NaN
+  fn division_operands(
+    &self,
+    left: &Object,
+    right: &Object,
+    operator: &Token,
+  ) -> Result<(f64, f64), RuntimeError> {
+    Self::check_num_operands(left, right, operator)?;
+    let (left, right) = (left.to_num().unwrap(), right.to_num().unwrap());
+
+    if self.strict_division && right == 0.0 {
+      return Err(Self::division_by_zero(&left, operator));
+    }
+
+    Ok((left, right))
+  }
+
+  /// `object[index]`
+  fn get_index(object: &Object, index: &Object, bracket: &Token) -> Result<Object, RuntimeError> {
+    match object {
+      Object::List(items) => {
+        let items = items.borrow();
+        let i = Self::check_index(items.len(), index, bracket)?;
+        Ok(items[i].clone())
+      }
+      Object::String(s) => {
+        let chars: Vec<char> = s.chars().collect();
+        let i = Self::check_index(chars.len(), index, bracket)?;
+        Ok(Object::String(chars[i].to_string()))
+      }
+      Object::Map(map) => {
+        Self::check_key(index, bracket)?;
+        map.borrow().get(index).cloned().ok_or_else(|| {
+          RuntimeError::KeyNotFound(
+            bracket.line,
+            bracket.column,
+            format!("no key {} in map", index.repr()),
+          )
+        })
+      }
+      _ => Err(RuntimeError::InvalidType(
+        bracket.line,
+        bracket.lexeme.clone(),
+        format!(
+          "can only index lists, maps and strings, not {}",
+          object.repr()
+        ),
+      )),
+    }
+  }
+
+  /// `object[index] = value`, evaluates to `value`
+  fn set_index(
+    &self,
+    object: &Object,
+    index: Object,
+    value: Object,
+    bracket: &Token,
+  ) -> Result<Object, RuntimeError> {
+    match object {
+      Object::List(items) => {
+        let i = Self::check_index(items.borrow().len(), &index, bracket)?;
+        self.budget.hold(object, &value)?;
+        let old = std::mem::replace(&mut items.borrow_mut()[i], value.clone());
+        self.budget.release(object, &old);
+        Ok(value)
       }
-      TokenType::Star => {
-        Self::check_num_operands(&left, &right, operator)?;
-        Ok(Object::Number(
-          left.to_num().unwrap() * right.to_num().unwrap(),
-        ))
+      Object::Map(map) => {
+        Self::check_key(&index, bracket)?;
+        self.budget.hold(object, &index)?;
+        self.budget.hold(object, &value)?;
+        let old = map.borrow_mut().insert(index.clone(), value.clone());
+        if let Some(old) = old {
+          self.budget.release(object, &index);
+          self.budget.release(object, &old);
+        }
+        Ok(value)
       }
+      _ => Err(RuntimeError::InvalidType(
+        bracket.line,
+        bracket.lexeme.clone(),
+        format!("can only assign into lists and maps, not {}", object.repr()),
+      )),
+    }
+  }
+
+  /// runs the file `path` names in its own globals the first time it's imported, later imports
+  /// get the same module back
+  fn import(&mut self, path: &Token) -> Result<Rc<Module>, RuntimeError> {
+    // refused before the path is even looked up, so a sandbox can't learn what files exist
+    if !self.capabilities.contains(Capabilities::MODULES) {
+      return Err(RuntimeError::PermissionDenied(
+        path.line,
+        path.lexeme.clone(),
+        "importing files is not allowed".to_string(),
+      ));
+    }
+
+    let file = self.modules.locate(path)?;
+    if let Some(module) = self.modules.cached(&file) {
+      return Ok(module);
+    }
+
+    let file_id = self.modules.enter(&file, path)?;
+    let result = self.run_module(&file, file_id, path);
+    let module = result.as_ref().ok().cloned();
+    self.modules.exit(module);
+    result
+  }
 
+  fn run_module(
+    &mut self,
+    file: &std::path::Path,
+    file_id: usize,
+    path: &Token,
+  ) -> Result<Rc<Module>, RuntimeError> {
+    let failed =
+      |msg: &str| RuntimeError::ImportFailed(path.line, path.lexeme.clone(), msg.to_string());
+
+    let source = std::fs::read_to_string(file).map_err(|e| failed(&e.to_string()))?;
+
+    let mut parser = Parser::new(Lexer::new_in_file(&source, file_id));
+    let parsed = parser.parse();
+    if let Some(e) = parser.lex_error() {
+      self.report(&LoxError::LexError(e.clone()));
+      return Err(failed("module has lexing errors"));
+    }
+
+    let Ok(statements) = parsed else {
+      for err in parser.errors() {
+        self.report(&LoxError::ParseError(err.clone()));
+      }
+      return Err(failed("module has parsing errors"));
+    };
+
+    let mut resolver = Resolver::new(self);
+    resolver.resolve_stmts(&statements);
+    let errors = resolver.into_errors();
+    if !errors.is_empty() {
+      errors.iter().for_each(|e| self.report(e));
+      return Err(failed("module has semantic errors"));
+    }
+
+    // the module runs against its own fresh globals, not the importer's
+    let globals = Rc::new(RefCell::new(Env::new(self.budget.heap())));
+    natives::define_natives(&mut globals.borrow_mut(), self.capabilities);
+
+    self.execute_block(&statements, Rc::clone(&globals))?;
+
+    Ok(Rc::new(Module {
+      name: file
+        .file_stem()
+        .map_or(String::new(), |s| s.to_string_lossy().to_string()),
+      globals,
+    }))
+  }
+
+  fn assign_variable(&mut self, name: &Token, value: &Object) -> Result<(), RuntimeError> {
+    match self.locals.get(&Expr::Variable { name: name.clone() }) {
+      Some(distance) => Env::assign_at(Rc::clone(&self.env), *distance, name, value)?,
+      None => Env::root(Rc::clone(&self.env))
+        .borrow_mut()
+        .assign(name, value)?,
+    }
+    Ok(())
+  }
+
+  fn look_up_variable(&self, name: &Token, expr: &Expr) -> Result<Object, RuntimeError> {
+    match self.locals.get(expr) {
+      Some(distance) => Env::get_at(Rc::clone(&self.env), *distance, &name.lexeme)
+        .ok_or_else(|| Env::not_found(name).into()),
+      // unresolved names are globals of whichever file the running code came from
+      None => Ok(Env::root(Rc::clone(&self.env)).borrow().get(name)?),
+    }
+  }
+}
+
+/// maps a possibly negative index (counting from the end) into `0..len`
+pub fn normalize_index(index: i64, len: usize) -> Option<usize> {
+  let index = if index < 0 {
+    index.checked_add_unsigned(len as u64)?
+  } else {
+    index
+  };
+  usize::try_from(index).ok().filter(|i| *i < len)
+}
+
+impl ExprVisitor<Result<Object, RuntimeError>> for Interpreter {
+  fn visit_literal_expr(&mut self, value: &Object) -> Result<Object, RuntimeError> {
+    Ok(value.clone())
+  }
+
+  fn visit_grouping_expr(&mut self, expression: &Expr) -> Result<Object, RuntimeError> {
+    expression.accept(self)
+  }
+
+  fn visit_unary_expr(&mut self, operator: &Token, right: &Expr) -> Result<Object, RuntimeError> {
+    let right = right.accept(self)?;
+    match operator.token_type {
+      TokenType::Minus => match right {
+        Object::Int(n) => self.int_result(
+          n.checked_neg(),
+          n.wrapping_neg(),
+          || format!("-({})", n),
+          operator,
+        ),
+        Object::BigInt(n) => Ok(Object::BigInt(Rc::new(-n.as_ref()))),
+        Object::Decimal(n) => Ok(Object::Decimal(Rc::new(-n.as_ref()))),
+        _ => match Self::check_num_operand(&right, operator) {
+          Ok(_) => Ok(Object::Number(-right.to_num().unwrap())),
+          Err(e) => Err(e),
+        },
+      },
+      TokenType::Tilde => match right {
+        Object::Int(n) => Ok(Object::Int(!n)),
+        _ => Err(RuntimeError::InvalidType(
+          operator.line,
+          operator.lexeme.clone(),
+          "bitwise operators only take 64-bit ints".to_string(),
+        )),
+      },
+      TokenType::Bang => Ok(Object::Bool(!right.to_bool())),
       _ => Ok(Object::None),
     }
   }
 
+  fn visit_binary_expr(
+    &mut self,
+    left: &Expr,
+    operator: &Token,
+    right: &Expr,
+  ) -> Result<Object, RuntimeError> {
+    let left = left.accept(self)?;
+    let right = right.accept(self)?;
+    self.binary_op(left, operator, right)
+  }
+
   fn visit_var_expr(&mut self, name: &Token) -> Result<Object, RuntimeError> {
     self.look_up_variable(name, &Expr::Variable { name: name.clone() })
   }
 
   fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> Result<Object, RuntimeError> {
     let ret_value = value.accept(self)?;
-
-    match self.locals.get(value) {
-      Some(distance) => Env::assign_at(Rc::clone(&self.env), *distance, name, &ret_value),
-      None => self.globals.borrow_mut().assign(name, &ret_value).unwrap()
-    }
-
+    self.assign_variable(name, &ret_value)?;
     Ok(ret_value)
   }
 
@@ -220,24 +937,184 @@ impl ExprVisitor<Result<Object, RuntimeError>> for Interpreter {
     right.accept(self)
   }
 
-  fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr]) -> Result<Object, RuntimeError> {
+  fn visit_call_expr(
+    &mut self,
+    callee: &Expr,
+    arguments: &[Expr],
+    named: &[(Token, Expr)],
+  ) -> Result<Object, RuntimeError> {
     let callee = callee.accept(self)?;
+    let (arguments, named) = self.evaluate_arguments(arguments, named)?;
+    self.call_with_named(&callee, &arguments, &named)
+  }
 
-    let mut ret_arguments = vec![];
-    for arg in arguments {
-      ret_arguments.push(arg.accept(self)?);
+  fn visit_interpolation_expr(
+    &mut self,
+    _start: &Token,
+    parts: &[Expr],
+  ) -> Result<Object, RuntimeError> {
+    let mut result = String::new();
+    for part in parts {
+      let value = part.accept(self)?;
+      result.push_str(&value.to_string());
     }
 
-    let function = callee.as_callable()?; // this contains the runtime type check
-    if ret_arguments.len() != function.arity() {
-      return Err(RuntimeError::InvalidNumArgs(format!(
-        "expected {} arguments, but got {}",
-        function.arity(),
-        ret_arguments.len()
-      )));
+    self.budget.reserve(result.len())?;
+    Ok(Object::String(result))
+  }
+
+  fn visit_conditional_expr(
+    &mut self,
+    condition: &Expr,
+    then_branch: &Expr,
+    else_branch: &Expr,
+  ) -> Result<Object, RuntimeError> {
+    // only the chosen branch is evaluated
+    if condition.accept(self)?.to_bool() {
+      then_branch.accept(self)
+    } else {
+      else_branch.accept(self)
     }
+  }
+
+  fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Object, RuntimeError> {
+    match object.accept(self)? {
+      Object::Module(module) => module.globals.borrow().get(name).map_err(|_| {
+        RuntimeError::ValueNotFound(
+          name.line,
+          name.lexeme.clone(),
+          format!("{} has no '{}'", module, name.lexeme),
+        )
+      }),
+      other => Err(RuntimeError::InvalidType(
+        name.line,
+        name.lexeme.clone(),
+        format!("{} has no properties", other),
+      )),
+    }
+  }
+
+  fn visit_lambda_expr(
+    &mut self,
+    keyword: &Token,
+    params: &Rc<[Param]>,
+    body: &Rc<[Stmt]>,
+  ) -> Result<Object, RuntimeError> {
+    // a lambda is a function declaration without a name, closing over the env it's evaluated in
+    let name = Token::new(
+      TokenType::Identifier,
+      "lambda",
+      Object::None,
+      keyword.line,
+      keyword.column,
+    );
+    Ok(Object::Callable(Rc::new(LoxFunction::new(
+      Stmt::Function {
+        name,
+        params: Rc::clone(params),
+        body: Rc::clone(body),
+        doc: None,
+      },
+      Rc::clone(&self.env),
+    ))))
+  }
+
+  fn visit_list_expr(
+    &mut self,
+    _bracket: &Token,
+    elements: &[Expr],
+  ) -> Result<Object, RuntimeError> {
+    let mut items = Vec::with_capacity(elements.len());
+    for element in elements {
+      items.push(element.accept(self)?);
+    }
+
+    let list = Object::new_list(items);
+    self.budget.track(&list)?;
+    Ok(list)
+  }
+
+  fn visit_map_expr(
+    &mut self,
+    brace: &Token,
+    entries: &[(Expr, Expr)],
+  ) -> Result<Object, RuntimeError> {
+    let mut map = LoxMap::default();
+    for (key, value) in entries {
+      let key = key.accept(self)?;
+      Self::check_key(&key, brace)?;
+      let value = value.accept(self)?;
+      map.insert(key, value);
+    }
+
+    let map = Object::new_map(map);
+    self.budget.track(&map)?;
+    Ok(map)
+  }
 
-    Ok(function.call(self, &ret_arguments)?)
+  fn visit_index_expr(
+    &mut self,
+    object: &Expr,
+    bracket: &Token,
+    index: &Expr,
+  ) -> Result<Object, RuntimeError> {
+    let object = object.accept(self)?;
+    let index = index.accept(self)?;
+    Self::get_index(&object, &index, bracket)
+  }
+
+  fn visit_index_set_expr(
+    &mut self,
+    object: &Expr,
+    bracket: &Token,
+    index: &Expr,
+    value: &Expr,
+  ) -> Result<Object, RuntimeError> {
+    let object = object.accept(self)?;
+    let index = index.accept(self)?;
+    let value = value.accept(self)?;
+    self.set_index(&object, index, value, bracket)
+  }
+
+  fn visit_compound_expr(
+    &mut self,
+    target: &Expr,
+    operator: &Token,
+    value: &Expr,
+    postfix: bool,
+  ) -> Result<Object, RuntimeError> {
+    // the target's parts are evaluated once, then read, updated and written back
+    let (old, new) = match target {
+      Expr::Variable { name } => {
+        let old = self.look_up_variable(name, target)?;
+        let value = value.accept(self)?;
+        let new = self.binary_op(old.clone(), operator, value)?;
+        self.assign_variable(name, &new)?;
+        (old, new)
+      }
+      Expr::Index {
+        object,
+        bracket,
+        index,
+      } => {
+        let object = object.accept(self)?;
+        let index = index.accept(self)?;
+        let old = Self::get_index(&object, &index, bracket)?;
+        let value = value.accept(self)?;
+        let new = self.binary_op(old.clone(), operator, value)?;
+        self.set_index(&object, index, new.clone(), bracket)?;
+        (old, new)
+      }
+      _ => {
+        return Err(RuntimeError::InvalidType(
+          operator.line,
+          operator.lexeme.clone(),
+          "invalid assignment target".to_string(),
+        ));
+      }
+    };
+
+    Ok(if postfix { old } else { new })
   }
 }
 
@@ -249,7 +1126,7 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
 
   fn visit_print_stmt(&mut self, expression: &Expr) -> Result<(), RuntimeError> {
     let value = expression.accept(self)?;
-    println!("{}", value);
+    writeln!(self.output, "{}", value)?;
     Ok(())
   }
 
@@ -257,6 +1134,7 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
     &mut self,
     name: &Token,
     initializer: &Option<Expr>,
+    constant: bool,
   ) -> Result<(), RuntimeError> {
     let mut value = Object::None;
     match initializer {
@@ -266,7 +1144,7 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
 
     // TODO: good place to put a warning that var is uninited or something
 
-    self.env.borrow_mut().define(&name.lexeme, &value);
+    self.env.borrow_mut().declare(name, &value, constant)?;
     Ok(())
   }
 
@@ -282,33 +1160,192 @@ impl StmtVisitor<Result<(), RuntimeError>> for Interpreter {
     else_branch: &Option<Stmt>,
   ) -> Result<(), RuntimeError> {
     if condition.accept(self)?.to_bool() {
-      then_branch.accept(self)?;
+      self.execute(then_branch)?;
     } else if let Some(e_branch) = else_branch {
-      e_branch.accept(self)?;
+      self.execute(e_branch)?;
     }
 
     Ok(())
   }
 
-  fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), RuntimeError> {
+  fn visit_while_stmt(
+    &mut self,
+    condition: &Expr,
+    body: &Stmt,
+    increment: &Option<Expr>,
+    label: &Option<Token>,
+  ) -> Result<(), RuntimeError> {
     while condition.accept(self)?.to_bool() {
-      body.accept(self)?;
+      let result = self.execute(body);
+      if !Self::keep_looping(result, label)? {
+        break;
+      }
+      if let Some(inc) = increment {
+        inc.accept(self)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn visit_for_in_stmt(
+    &mut self,
+    name: &Token,
+    iterable: &Expr,
+    body: &Stmt,
+    label: &Option<Token>,
+  ) -> Result<(), RuntimeError> {
+    // runs one iteration and tells whether to go on with the next
+    let iteration = |interpreter: &mut Self, item: &Object| {
+      let result = interpreter.execute_iteration(name, item, body);
+      Self::keep_looping(result, label)
+    };
+
+    match iterable.accept(self)? {
+      // indexed live, so pushing onto the list inside the loop gets iterated too
+      Object::List(list) => {
+        let mut i = 0;
+        loop {
+          // the borrow has to end before the body runs, it may change the list
+          let item = list.borrow().get(i).cloned();
+          let Some(item) = item else {
+            break;
+          };
+          if !iteration(self, &item)? {
+            break;
+          }
+          i += 1;
+        }
+      }
+      Object::Map(map) => {
+        let keys: Vec<Object> = map.borrow().keys().cloned().collect();
+        for key in keys {
+          if !iteration(self, &key)? {
+            break;
+          }
+        }
+      }
+      Object::String(s) => {
+        for c in s.chars() {
+          if !iteration(self, &Object::String(c.to_string()))? {
+            break;
+          }
+        }
+      }
+      // iterator protocol: anything callable without arguments is called until it returns none
+      iterator @ Object::Callable(_) => loop {
+        let item = self.call(&iterator, &[])?;
+        if item == Object::None {
+          break;
+        }
+        if !iteration(self, &item)? {
+          break;
+        }
+      },
+      other => {
+        return Err(RuntimeError::InvalidType(
+          name.line,
+          name.lexeme.clone(),
+          format!("can't iterate over {}", other),
+        ));
+      }
+    }
+
+    Ok(())
+  }
+
+  fn visit_break_stmt(
+    &mut self,
+    _keyword: &Token,
+    label: &Option<Token>,
+  ) -> Result<(), RuntimeError> {
+    // unwinds like return does, up to the loop it belongs to
+    Err(RuntimeError::BreakCalled(
+      label.as_ref().map(|l| l.lexeme.clone()),
+    ))
+  }
+
+  fn visit_continue_stmt(
+    &mut self,
+    _keyword: &Token,
+    label: &Option<Token>,
+  ) -> Result<(), RuntimeError> {
+    Err(RuntimeError::ContinueCalled(
+      label.as_ref().map(|l| l.lexeme.clone()),
+    ))
+  }
+
+  fn visit_import_stmt(
+    &mut self,
+    path: &Token,
+    alias: &Option<Token>,
+    names: &[Token],
+  ) -> Result<(), RuntimeError> {
+    let module = self.import(path)?;
+
+    if let Some(alias) = alias {
+      let value = Object::Module(Rc::clone(&module));
+      self.env.borrow_mut().define(&alias.lexeme, &value);
+    }
+
+    for name in names {
+      let value = module.globals.borrow().get(name).map_err(|_| {
+        RuntimeError::ImportFailed(
+          name.line,
+          path.lexeme.clone(),
+          format!("{} has no '{}'", module, name.lexeme),
+        )
+      })?;
+      self.env.borrow_mut().define(&name.lexeme, &value);
     }
 
     Ok(())
   }
 
+  fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> Result<(), RuntimeError> {
+    let value = value.accept(self)?;
+    Err(RuntimeError::Thrown(keyword.line, value))
+  }
+
+  fn visit_try_stmt(
+    &mut self,
+    body: &[Stmt],
+    catch: &Option<(Token, Vec<Stmt>)>,
+    finally: &Option<Vec<Stmt>>,
+  ) -> Result<(), RuntimeError> {
+    let env = Rc::new(RefCell::new(Env::new_enclosing(self.env.clone())));
+    let mut result = self.execute_block(body, env);
+
+    if let (Err(err), Some((name, handler))) = (&result, catch)
+      && let Some(error) = err.to_object()
+    {
+      let mut env = Env::new_enclosing(self.env.clone());
+      env.define(&name.lexeme, &error);
+      result = self.execute_block(handler, Rc::new(RefCell::new(env)));
+    }
+
+    // finally always runs, if it finishes normally whatever happened before carries on,
+    // be that an error, a return or a break
+    if let Some(finally) = finally {
+      let env = Rc::new(RefCell::new(Env::new_enclosing(self.env.clone())));
+      self.execute_block(finally, env)?;
+    }
+
+    result
+  }
+
   fn visit_function_stmt(
     &mut self,
     name: &Token,
-    params: &Vec<Token>,
-    body: &Vec<Stmt>,
+    params: &Rc<[Param]>,
+    body: &Rc<[Stmt]>,
   ) -> Result<(), RuntimeError> {
     let function = Object::Callable(Rc::new(LoxFunction::new(
       Stmt::Function {
         name: name.clone(),
-        params: params.to_vec(),
-        body: body.to_vec(),
+        params: Rc::clone(params),
+        body: Rc::clone(body),
+        doc: None,
       },
       Rc::clone(&self.env),
     )));
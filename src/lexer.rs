@@ -1,8 +1,12 @@
 use crate::{
+  bignum::{BigInt, Decimal},
   errors::LexError,
   types::{Object, Token, TokenType},
 };
-use std::collections::HashMap;
+use std::{
+  collections::{HashMap, VecDeque},
+  rc::Rc,
+};
 
 trait CharCheck {
   fn is_lalpha(&self) -> bool;
@@ -10,8 +14,9 @@ trait CharCheck {
 }
 
 impl CharCheck for char {
+  /// any letter can start an identifier, not just ascii ones
   fn is_lalpha(&self) -> bool {
-    (*self >= 'a' && *self <= 'z') || (*self >= 'A' && *self <= 'Z') || *self == '_'
+    self.is_alphabetic() || *self == '_'
   }
 
   fn is_lalphanumeric(&self) -> bool {
@@ -19,13 +24,107 @@ impl CharCheck for char {
   }
 }
 
+/// replaces escape sequences with the characters they stand for, `line` is where `s` starts
+fn unescape(s: &str, mut line: usize) -> Result<String, LexError> {
+  let invalid = |line: usize, sequence: String, msg: &str| {
+    Err(LexError::InvalidEscape(line, sequence, msg.to_string()))
+  };
+
+  let mut value = String::with_capacity(s.len());
+  let mut chars = s.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      if c == '\n' {
+        line += 1;
+      }
+      value.push(c);
+      continue;
+    }
+
+    match chars.next() {
+      Some('n') => value.push('\n'),
+      Some('t') => value.push('\t'),
+      Some('r') => value.push('\r'),
+      Some('0') => value.push('\0'),
+      Some('\\') => value.push('\\'),
+      Some('"') => value.push('"'),
+      Some('$') => value.push('$'),
+      Some('u') => {
+        if chars.next() != Some('{') {
+          return invalid(line, "\\u".to_string(), "expect '{' after \\u");
+        }
+        let digits: String = chars.by_ref().take_while(|&c| c != '}').collect();
+        let sequence = format!("\\u{{{}}}", digits);
+        if digits.is_empty() || digits.len() > 6 {
+          return invalid(line, sequence, "expect 1 to 6 hex digits in \\u{...}");
+        }
+        match u32::from_str_radix(&digits, 16)
+          .ok()
+          .and_then(char::from_u32)
+        {
+          Some(c) => value.push(c),
+          None => return invalid(line, sequence, "not a unicode scalar value"),
+        }
+      }
+      Some(other) => return invalid(line, format!("\\{}", other), "unknown escape sequence"),
+      None => return invalid(line, "\\".to_string(), "nothing to escape at end of string"),
+    }
+  }
+
+  Ok(value)
+}
+
+/// drops a blank first and last line and the indentation every other line shares, returns the
+/// result and how many lines were dropped from the start
+fn dedent(s: &str) -> (String, usize) {
+  let mut lines: Vec<&str> = s.split('\n').collect();
+  let mut skipped = 0;
+  if lines.len() > 1 && lines[0].trim().is_empty() {
+    lines.remove(0);
+    skipped = 1;
+  }
+  if lines.len() > 1 && lines.last().is_some_and(|l| l.trim().is_empty()) {
+    lines.pop();
+  }
+
+  let indent = lines
+    .iter()
+    .filter(|l| !l.trim().is_empty())
+    .map(|l| l.chars().take_while(|c| *c == ' ' || *c == '\t').count())
+    .min()
+    .unwrap_or(0);
+
+  let lines: Vec<String> = lines
+    .iter()
+    .map(|l| l.chars().skip(indent).collect())
+    .collect();
+  (lines.join("\n"), skipped)
+}
+
+/// a string literal that has been lexed up to some point
+struct OpenString {
+  raw: bool,
+  triple: bool,
+  depth: usize,                       // braces opened inside the current `${...}`
+  parts: Vec<(usize, String, usize)>, // token index, text still to unescape, line it starts on
+  start_line: usize,
+}
+
+/// produces tokens on demand, as an iterator that ends after the `Eof` token or the first error
 pub struct Lexer {
   source: String,
-  tokens: Vec<Token>,
+  tokens: VecDeque<Token>, // lexed but not handed out yet, held back while a string is open
+  last: Option<(TokenType, usize)>, // type and line of the last token lexed
+  done: bool,
 
-  start: usize,
-  current: usize,
+  start: usize,   // byte offset of the current token
+  current: usize, // byte offset of the next char, always on a char boundary
   line: usize,
+  column: usize,                 // chars consumed on the current line
+  open_strings: Vec<OpenString>, // strings whose `${...}` is being lexed, innermost last
+  start_line: usize, // line and column the current token starts at, strings can span lines
+  start_column: usize,
+  file: usize,
 
   keywords: HashMap<String, TokenType>,
 }
@@ -34,33 +133,59 @@ impl Lexer {
   pub fn new(source: &str) -> Self {
     let keywords = HashMap::from([
       ("and".to_string(), TokenType::And),
+      ("as".to_string(), TokenType::As),
+      ("break".to_string(), TokenType::Break),
+      ("catch".to_string(), TokenType::Catch),
       ("class".to_string(), TokenType::Class),
+      ("const".to_string(), TokenType::Const),
+      ("continue".to_string(), TokenType::Continue),
       ("else".to_string(), TokenType::Else),
       ("false".to_string(), TokenType::False),
+      ("finally".to_string(), TokenType::Finally),
       ("for".to_string(), TokenType::For),
+      ("from".to_string(), TokenType::From),
       ("func".to_string(), TokenType::Func),
       ("if".to_string(), TokenType::If),
+      ("import".to_string(), TokenType::Import),
+      ("in".to_string(), TokenType::In),
       ("none".to_string(), TokenType::None),
       ("or".to_string(), TokenType::Or),
       ("print".to_string(), TokenType::Print),
       ("return".to_string(), TokenType::Return),
       ("super".to_string(), TokenType::Super),
       ("this".to_string(), TokenType::This),
+      ("throw".to_string(), TokenType::Throw),
       ("true".to_string(), TokenType::True),
+      ("try".to_string(), TokenType::Try),
       ("var".to_string(), TokenType::Var),
       ("while".to_string(), TokenType::While),
     ]);
 
     Lexer {
       source: source.to_owned(),
-      tokens: vec![],
+      tokens: VecDeque::new(),
+      last: None,
+      done: false,
       start: 0,
       current: 0,
       line: 0,
+      column: 0,
+      open_strings: vec![],
+      start_line: 0,
+      start_column: 0,
+      file: 0,
       keywords,
     }
   }
 
+  /// lexes one parsed source, `file` keeps its tokens apart from same-placed ones in other files
+  pub fn new_in_file(source: &str, file: usize) -> Self {
+    Self {
+      file,
+      ..Self::new(source)
+    }
+  }
+
   fn is_at_end(&self) -> bool {
     self.current >= self.source.len()
   }
@@ -70,14 +195,77 @@ impl Lexer {
     match c {
       '(' => self.add_token(TokenType::LeftParen),
       ')' => self.add_token(TokenType::RightParen),
-      '{' => self.add_token(TokenType::LeftBrace),
-      '}' => self.add_token(TokenType::RightBrace),
+      '{' => {
+        if let Some(string) = self.open_strings.last_mut() {
+          string.depth += 1;
+        }
+        self.add_token(TokenType::LeftBrace)
+      }
+      '}' => match self.open_strings.pop() {
+        // closes an embedded expression, the string it's in carries on
+        Some(string) if string.depth == 0 => self.string_part(string)?,
+        Some(mut string) => {
+          string.depth -= 1;
+          self.open_strings.push(string);
+          self.add_token(TokenType::RightBrace)
+        }
+        None => self.add_token(TokenType::RightBrace),
+      },
+      '[' => self.add_token(TokenType::LeftBracket),
+      ']' => self.add_token(TokenType::RightBracket),
       ',' => self.add_token(TokenType::Comma),
+      ':' => self.add_token(TokenType::Colon),
+      '.' if self.peek()?.is_ascii_digit() && !self.follows_operand() => self.number()?,
+      '.' if self.peek()? == '.' && self.peek_next()? == '.' => {
+        self.advance()?;
+        self.advance()?;
+        self.add_token(TokenType::Ellipsis)
+      }
       '.' => self.add_token(TokenType::Dot),
-      '-' => self.add_token(TokenType::Minus),
-      '+' => self.add_token(TokenType::Plus),
+      '-' => {
+        let token_type = if self.amatch('=')? {
+          TokenType::MinusEqual
+        } else if self.amatch('-')? {
+          TokenType::MinusMinus
+        } else {
+          TokenType::Minus
+        };
+        self.add_token(token_type)
+      }
+      '|' => self.add_token(TokenType::Pipe),
+      '&' => self.add_token(TokenType::Ampersand),
+      '^' => self.add_token(TokenType::Caret),
+      '~' => {
+        let token_type = if self.amatch('/')? {
+          TokenType::TildeSlash
+        } else {
+          TokenType::Tilde
+        };
+        self.add_token(token_type)
+      }
+      '+' => {
+        let token_type = if self.amatch('=')? {
+          TokenType::PlusEqual
+        } else if self.amatch('+')? {
+          TokenType::PlusPlus
+        } else {
+          TokenType::Plus
+        };
+        self.add_token(token_type)
+      }
       ';' => self.add_token(TokenType::Semicolon),
-      '*' => self.add_token(TokenType::Star),
+      '*' => {
+        let token_type = if self.amatch('=')? {
+          TokenType::StarEqual
+        } else if self.amatch('*')? {
+          TokenType::StarStar
+        } else {
+          TokenType::Star
+        };
+        self.add_token(token_type)
+      }
+      '%' => self.add_token(TokenType::Percent),
+      '?' => self.add_token(TokenType::Question),
       '!' => {
         let amatch = self.amatch('=')?;
         self.add_token(if amatch {
@@ -95,26 +283,39 @@ impl Lexer {
         })
       }
       '<' => {
-        let amatch = self.amatch('=')?;
-        self.add_token(if amatch {
+        let token_type = if self.amatch('=')? {
           TokenType::LessEqual
+        } else if self.amatch('<')? {
+          TokenType::LessLess
         } else {
           TokenType::Less
-        })
+        };
+        self.add_token(token_type)
       }
       '>' => {
-        let amatch = self.amatch('=')?;
-        self.add_token(if amatch {
+        let token_type = if self.amatch('=')? {
           TokenType::GreaterEqual
+        } else if self.amatch('>')? {
+          TokenType::GreaterGreater
         } else {
           TokenType::Greater
-        })
+        };
+        self.add_token(token_type)
       }
       '/' => {
         if self.amatch('/')? {
+          // exactly three slashes make a doc comment, four or more are an ordinary comment
+          let doc = self.peek()? == '/' && self.peek_next()? != '/';
           while self.peek()? != '\n' && !self.is_at_end() {
             self.advance()?;
           }
+          if doc {
+            self.doc_comment();
+          }
+        } else if self.amatch('*')? {
+          self.block_comment()?;
+        } else if self.amatch('=')? {
+          self.add_token(TokenType::SlashEqual)
         } else {
           self.add_token(TokenType::Slash)
         }
@@ -122,10 +323,14 @@ impl Lexer {
       ' ' => {}
       '\r' => {}
       '\t' => {}
-      '\n' => self.line += 1,
-      '"' => self.string()?,
+      '\n' => self.newline(),
+      '"' => self.string(false)?,
+      'r' if self.peek()? == '"' => {
+        self.advance()?;
+        self.string(true)?
+      }
       _ => {
-        if c.is_numeric() {
+        if c.is_ascii_digit() {
           self.number()?;
         } else if c.is_lalpha() {
           self.identifier()?;
@@ -158,97 +363,337 @@ impl Lexer {
     Ok(())
   }
 
+  /// called after the first digit, or after a '.' that's followed by one
   fn number(&mut self) -> Result<(), LexError> {
-    while self.peek()?.is_numeric() {
+    let radix = match (&self.source[self.start..self.current], self.peek()?) {
+      ("0", 'x' | 'X') => 16,
+      ("0", 'o' | 'O') => 8,
+      ("0", 'b' | 'B') => 2,
+      _ => 10,
+    };
+
+    if radix != 10 {
       self.advance()?;
+      self.digits(radix)?;
+      let bigint = self.suffix(&['n'])?.is_some();
+      self.end_of_number(radix)?;
+
+      let digits = self.source[self.start + 2..self.current - bigint as usize].replace('_', "");
+      if digits.is_empty() {
+        return Err(self.invalid_number("expected digits after the prefix"));
+      }
+      let value = if bigint {
+        Object::BigInt(Rc::new(BigInt::parse(&digits, radix).unwrap()))
+      } else {
+        Object::Int(i64::from_str_radix(&digits, radix).map_err(|_| {
+          self.invalid_number("literal doesn't fit in a 64-bit integer, add 'n' for a bigint")
+        })?)
+      };
+      self.add_token_literal(TokenType::Number, value);
+      return Ok(());
     }
 
-    if self.peek()? == '.' && self.peek_next()?.is_numeric() {
+    self.digits(10)?;
+    if self.peek()? == '.' && self.peek_next()?.is_ascii_digit() {
       self.advance()?;
+      self.digits(10)?;
+    }
 
-      while self.peek()?.is_numeric() {
-        self.advance()?;
+    if matches!(self.peek()?, 'e' | 'E') {
+      self.advance()?;
+      if !self.amatch('+')? {
+        self.amatch('-')?;
+      }
+      if !self.peek()?.is_ascii_digit() {
+        return Err(self.invalid_number("expected digits in the exponent"));
       }
+      self.digits(10)?;
     }
-
-    let s = &self.source[self.start..self.current];
-    let float_literal = match s.parse::<f64>() {
-      Ok(num) => num,
-      Err(e) => return Err(LexError::ParseFloatError(e)),
+    let suffix = self.suffix(&['n', 'd'])?;
+    self.end_of_number(radix)?;
+
+    // without a suffix, fraction or exponent the literal is an int
+    let text = self.source[self.start..self.current - suffix.is_some() as usize].replace('_', "");
+    let fraction = text.contains(['.', 'e', 'E']);
+    let value = match suffix {
+      Some('n') if fraction => {
+        return Err(self.invalid_number("a bigint literal can't have a fraction or exponent"));
+      }
+      Some('n') => Object::BigInt(Rc::new(BigInt::parse(&text, 10).unwrap())),
+      Some(_) => Object::Decimal(Rc::new(
+        Decimal::parse(&text)
+          .ok_or_else(|| self.invalid_number("decimal exponent out of range"))?,
+      )),
+      None if fraction => Object::Number(
+        text
+          .parse()
+          .map_err(|e: std::num::ParseFloatError| self.invalid_number(&e.to_string()))?,
+      ),
+      None => Object::Int(text.parse().map_err(|_| {
+        self.invalid_number(
+          "literal doesn't fit in a 64-bit integer, add 'n' for a bigint or '.0' for a float",
+        )
+      })?),
     };
-    self.add_token_literal(TokenType::Number, Object::Number(float_literal));
+    self.add_token_literal(TokenType::Number, value);
 
     Ok(())
   }
 
-  fn string(&mut self) -> Result<(), LexError> {
-    while self.peek()? != '"' && !self.is_at_end() {
-      if self.peek()? == '\n' {
-        self.line += 1;
-      }
+  /// consumes the type suffix the number ends in, if it's one of `suffixes`
+  fn suffix(&mut self, suffixes: &[char]) -> Result<Option<char>, LexError> {
+    let c = self.peek()?;
+    if suffixes.contains(&c) && !self.peek_next()?.is_lalphanumeric() {
       self.advance()?;
+      return Ok(Some(c));
     }
+    Ok(None)
+  }
 
-    if self.is_at_end() {
-      return Err(LexError::IncompleteString(
-        self.line,
-        "slice of source (entire line)".to_string(),
-        "unterminated string".to_string(),
-      ));
+  /// digits in `radix`, optionally grouped with '_'
+  fn digits(&mut self, radix: u32) -> Result<(), LexError> {
+    while self.peek()?.is_digit(radix) || self.peek()? == '_' {
+      self.advance()?;
     }
+    Ok(())
+  }
 
-    self.advance()?; // closing "
+  /// a number can't run straight into letters or digits it doesn't use, and its '_'s have to sit
+  /// between two digits
+  fn end_of_number(&mut self, radix: u32) -> Result<(), LexError> {
+    if self.peek()?.is_lalphanumeric() {
+      let c = self.advance()?;
+      while self.peek()?.is_lalphanumeric() {
+        self.advance()?;
+      }
+      return Err(self.invalid_number(&match radix {
+        16 => format!("invalid digit '{}' in a hexadecimal literal", c),
+        8 => format!("invalid digit '{}' in an octal literal", c),
+        2 => format!("invalid digit '{}' in a binary literal", c),
+        _ => format!("unexpected '{}' after a number", c),
+      }));
+    }
 
-    let value = &self.source[self.start + 1..self.current - 1];
-    self.add_token_literal(TokenType::LoxString, Object::String(value.to_string()));
+    let text: Vec<char> = self.source[self.start..self.current].chars().collect();
+    let misplaced = text.iter().enumerate().any(|(i, c)| {
+      *c == '_'
+        && !(i > 0
+          && text[i - 1].is_digit(radix)
+          && text.get(i + 1).is_some_and(|next| next.is_digit(radix)))
+    });
+    if misplaced {
+      return Err(self.invalid_number("'_' can only separate digits"));
+    }
 
     Ok(())
   }
 
-  fn peek(&self) -> Result<char, LexError> {
-    if self.is_at_end() {
-      Ok('\0')
-    } else {
-      match self.source.chars().nth(self.current) {
-        Some(c) => Ok(c),
-        None => Err(LexError::Eof),
+  fn invalid_number(&self, msg: &str) -> LexError {
+    LexError::InvalidNumber(
+      self.start_line,
+      self.source[self.start..self.current].to_string(),
+      msg.to_string(),
+    )
+  }
+
+  /// called after the opening '/*', block comments nest
+  fn block_comment(&mut self) -> Result<(), LexError> {
+    let mut depth = 1;
+    while depth > 0 {
+      if self.is_at_end() {
+        return Err(LexError::IncompleteComment(
+          self.start_line,
+          "/*".to_string(),
+          "unterminated block comment".to_string(),
+        ));
+      }
+
+      match self.advance()? {
+        '/' if self.peek()? == '*' => {
+          self.advance()?;
+          depth += 1;
+        }
+        '*' if self.peek()? == '/' => {
+          self.advance()?;
+          depth -= 1;
+        }
+        '\n' => self.newline(),
+        _ => {}
       }
     }
+
+    Ok(())
   }
 
-  fn peek_next(&self) -> Result<char, LexError> {
-    if self.current + 1 >= self.source.len() {
-      Ok('\0')
+  /// called at the end of a '///' line
+  fn doc_comment(&mut self) {
+    let line: String = self.source[self.start + 3..self.current].to_string();
+    let text = line
+      .strip_prefix(' ')
+      .unwrap_or(&line)
+      .trim_end()
+      .to_string();
+    self.add_token_literal(TokenType::DocComment, Object::String(text));
+  }
+
+  /// called after the opening '"'. raw strings (r"...") keep backslashes as they are, triple
+  /// quoted strings ("""...""") have their common indentation stripped
+  fn string(&mut self, raw: bool) -> Result<(), LexError> {
+    let triple = self.peek()? == '"' && self.peek_next()? == '"';
+    if triple {
+      self.advance()?;
+      self.advance()?;
+    }
+
+    let string = OpenString {
+      raw,
+      triple,
+      depth: 0,
+      parts: vec![],
+      start_line: self.line,
+    };
+    self.string_part(string)
+  }
+
+  /// lexes string text up to either the closing quote or the next `${`, which leaves the string
+  /// open while the embedded expression is lexed as ordinary tokens
+  fn string_part(&mut self, mut string: OpenString) -> Result<(), LexError> {
+    let part_line = self.line;
+    let mut body = String::new();
+    let interpolates = loop {
+      if self.is_at_end() {
+        return Err(LexError::IncompleteString(
+          string.start_line,
+          "slice of source (entire line)".to_string(),
+          "unterminated string".to_string(),
+        ));
+      }
+
+      let c = self.advance()?;
+      match c {
+        '"' if !string.triple => break false,
+        '"' if self.peek()? == '"' && self.peek_next()? == '"' => {
+          self.advance()?;
+          self.advance()?;
+          break false;
+        }
+        '$' if !string.raw && self.peek()? == '{' => {
+          self.advance()?;
+          break true;
+        }
+        // an escaped character never ends the string, even in raw strings
+        '\\' if !self.is_at_end() => {
+          body.push(c);
+          let escaped = self.advance()?;
+          if escaped == '\n' {
+            self.newline();
+          }
+          body.push(escaped);
+        }
+        '\n' => {
+          self.newline();
+          body.push(c);
+        }
+        _ => body.push(c),
+      }
+    };
+
+    let token_type = if interpolates {
+      TokenType::Interpolation
     } else {
-      match self.source.chars().nth(self.current + 1) {
-        Some(c) => Ok(c),
-        None => Err(LexError::Eof),
+      TokenType::LoxString
+    };
+    self.add_token(token_type);
+    string.parts.push((self.tokens.len() - 1, body, part_line));
+
+    if interpolates {
+      self.open_strings.push(string);
+    } else {
+      self.finish_string(string)?;
+    }
+
+    Ok(())
+  }
+
+  /// fills in the literal of every part of a closed string, only possible once all of it is known
+  /// as the common indentation of a triple quoted string can depend on any of its parts
+  fn finish_string(&mut self, string: OpenString) -> Result<(), LexError> {
+    let mut parts = string.parts;
+
+    if string.triple {
+      // embedded expressions stand in as a non-blank placeholder while dedenting
+      let joined: Vec<&str> = parts.iter().map(|(_, body, _)| body.as_str()).collect();
+      let (dedented, skipped) = dedent(&joined.join("\0"));
+      for ((_, body, _), dedented) in parts.iter_mut().zip(dedented.split('\0')) {
+        *body = dedented.to_string();
       }
+      parts[0].2 += skipped;
+    }
+
+    for (index, body, line) in parts {
+      let value = if string.raw {
+        body
+      } else {
+        unescape(&body, line)?
+      };
+      self.tokens[index].literal = Object::String(value);
     }
+
+    Ok(())
+  }
+
+  /// whether the last token ends an operand on this line, which makes a following `.` a dot even
+  /// when a digit comes next
+  fn follows_operand(&self) -> bool {
+    self.last.as_ref().is_some_and(|(token_type, line)| {
+      *line == self.line
+        && matches!(
+          token_type,
+          TokenType::Number
+            | TokenType::LoxString
+            | TokenType::Identifier
+            | TokenType::RightParen
+            | TokenType::RightBracket
+            | TokenType::True
+            | TokenType::False
+            | TokenType::None
+        )
+    })
+  }
+
+  /// the chars not consumed yet, `current` always sits on a char boundary so slicing is cheap
+  fn rest(&self) -> std::str::Chars<'_> {
+    self.source[self.current..].chars()
+  }
+
+  fn peek(&self) -> Result<char, LexError> {
+    Ok(self.rest().next().unwrap_or('\0'))
+  }
+
+  fn peek_next(&self) -> Result<char, LexError> {
+    Ok(self.rest().nth(1).unwrap_or('\0'))
   }
 
   fn amatch(&mut self, expected: char) -> Result<bool, LexError> {
-    if self.is_at_end() {
+    if self.is_at_end() || self.peek()? != expected {
       return Ok(false);
     }
 
-    match self.source.chars().nth(self.current) {
-      Some(curr_char) if curr_char == expected => {
-        self.current += 1;
-        Ok(true)
-      }
-      Some(_) => Ok(false),
-      None => Err(LexError::Eof),
-    }
+    self.advance()?;
+    Ok(true)
   }
 
   fn advance(&mut self) -> Result<char, LexError> {
-    let c = self.source.chars().nth(self.current);
-    self.current += 1;
-    match c {
-      Some(c) => Ok(c),
-      None => Err(LexError::Eof),
-    }
+    let c = self.rest().next().ok_or(LexError::Eof)?;
+    self.current += c.len_utf8();
+    self.column += 1;
+    Ok(c)
+  }
+
+  /// called after consuming a '\n'
+  fn newline(&mut self) {
+    self.line += 1;
+    self.column = 0;
   }
 
   fn add_token(&mut self, token_type: TokenType) {
@@ -256,23 +701,197 @@ impl Lexer {
   }
 
   fn add_token_literal(&mut self, token_type: TokenType, literal: Object) {
-    self.tokens.push(Token::new(
-      token_type,
-      &self.source[self.start..self.current],
-      literal,
-      self.line,
-    ))
+    self.last = Some((token_type.clone(), self.start_line));
+    self.tokens.push_back(Token {
+      file: self.file,
+      ..Token::new(
+        token_type,
+        &self.source[self.start..self.current],
+        literal,
+        self.start_line,
+        self.start_column,
+      )
+    })
   }
 
-  pub fn lex_tokens(&mut self) -> Result<&Vec<Token>, LexError> {
-    while !self.is_at_end() {
+  /// lexes what's left of the source in one go
+  pub fn lex_tokens(&mut self) -> Result<Vec<Token>, LexError> {
+    self.collect()
+  }
+}
+
+impl Iterator for Lexer {
+  type Item = Result<Token, LexError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      // the parts of a string only get their literal once the whole string is lexed
+      if self.open_strings.is_empty() && !self.tokens.is_empty() {
+        return self.tokens.pop_front().map(Ok);
+      }
+
+      if self.done {
+        return None;
+      }
+
+      if self.is_at_end() {
+        self.done = true;
+        if let Some(string) = self.open_strings.first() {
+          return Some(Err(LexError::IncompleteString(
+            string.start_line,
+            "slice of source (entire line)".to_string(),
+            "unterminated '${' in string".to_string(),
+          )));
+        }
+
+        self.tokens.push_back(Token {
+          file: self.file,
+          ..Token::new(TokenType::Eof, "", Object::None, self.line, self.column)
+        });
+        continue;
+      }
+
       self.start = self.current;
-      self.lex_token()?;
+      self.start_line = self.line;
+      self.start_column = self.column;
+      if let Err(e) = self.lex_token() {
+        self.done = true;
+        self.tokens.clear();
+        return Some(Err(e));
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod lexer_tests {
+  use super::*;
+
+  fn string_literal(source: &str) -> Result<String, LexError> {
+    let mut lexer = Lexer::new(source);
+    let tokens = lexer.lex_tokens()?;
+    Ok(tokens[0].literal.to_str().unwrap())
+  }
+
+  #[test]
+  fn string_escapes() {
+    assert_eq!(
+      string_literal(r#""a\tb\n\"c\"\\""#).unwrap(),
+      "a\tb\n\"c\"\\"
+    );
+    assert_eq!(
+      string_literal(r#""\u{48}\u{1F600}""#).unwrap(),
+      "H\u{1F600}"
+    );
+    assert_eq!(string_literal(r#"r"C:\new\dir""#).unwrap(), r"C:\new\dir");
+    assert_eq!(
+      string_literal("\"\"\"\n    one\n      two\n    \"\"\"").unwrap(),
+      "one\n  two"
+    );
+
+    for (source, sequence) in [
+      (r#""\q""#, r"\q"),
+      (r#""\u{110000}""#, r"\u{110000}"),
+      (r#""\u41""#, r"\u"),
+    ] {
+      match string_literal(source) {
+        Err(LexError::InvalidEscape(0, s, _)) => assert_eq!(s, sequence),
+        other => panic!("expected an invalid escape for {}, got {:?}", source, other),
+      }
     }
+  }
+
+  #[test]
+  fn number_literals() {
+    let number = |source: &str| -> Result<Object, LexError> {
+      let mut lexer = Lexer::new(source);
+      let tokens = lexer.lex_tokens()?;
+      Ok(tokens[0].literal.clone())
+    };
+
+    assert!(matches!(number("0xFF").unwrap(), Object::Int(255)));
+    assert!(matches!(number("0b1010").unwrap(), Object::Int(10)));
+    assert!(matches!(number("0o17").unwrap(), Object::Int(15)));
+    assert!(matches!(
+      number("1_000_000").unwrap(),
+      Object::Int(1_000_000)
+    ));
+    assert!(matches!(
+      number("9007199254740993").unwrap(),
+      Object::Int(9007199254740993)
+    ));
+    assert!(matches!(number("1e-9").unwrap(), Object::Number(n) if n == 1e-9));
+    assert!(matches!(number(".5").unwrap(), Object::Number(n) if n == 0.5));
+    assert!(matches!(number("2.0").unwrap(), Object::Number(n) if n == 2.0));
+    assert_eq!(number("0xFFn").unwrap().to_string(), "255");
+    assert!(matches!(
+      number("9223372036854775808n").unwrap(),
+      Object::BigInt(_)
+    ));
+    assert!(matches!(number("1.10d").unwrap(), Object::Decimal(n) if n.to_string() == "1.10"));
+
+    for source in [
+      "0x",
+      "0b12",
+      "1__0",
+      "1_",
+      "1e",
+      "12abc",
+      "0x_1",
+      "9223372036854775808",
+      "1.5n",
+      "1_n",
+    ] {
+      match number(source) {
+        Err(LexError::InvalidNumber(0, literal, _)) => assert_eq!(literal, source),
+        Err(other) => panic!("expected an invalid number for {}, got {:?}", source, other),
+        Ok(value) => panic!("expected an invalid number for {}, got {}", source, value),
+      }
+    }
+  }
+
+  #[test]
+  fn tokens_come_on_demand() {
+    // everything before an error is handed out before the error is even reached
+    let mut lexer = Lexer::new("var a = 1; @");
+    let lexemes: Vec<String> = lexer
+      .by_ref()
+      .take(4)
+      .map(|token| token.unwrap().lexeme)
+      .collect();
+    assert_eq!(lexemes, vec!["var", "a", "=", "1"]);
+    assert_eq!(lexer.next().unwrap().unwrap().lexeme, ";");
+    assert!(matches!(lexer.next(), Some(Err(LexError::UnknownChar(..)))));
+    assert!(lexer.next().is_none());
+
+    // the parts of an interpolated string are held back until the string closes
+    let tokens = Lexer::new("\"\"\"\n  a ${x}\n  b\"\"\"")
+      .lex_tokens()
+      .unwrap();
+    let literals: Vec<String> = tokens.iter().map(|t| t.literal.to_string()).collect();
+    assert_eq!(literals, vec!["a ", "none", "\nb", "none"]);
+  }
 
-    self
-      .tokens
-      .push(Token::new(TokenType::Eof, "", Object::None, self.line));
-    Ok(&self.tokens)
+  #[test]
+  fn unicode_positions() {
+    let mut lexer = Lexer::new("var é = \"ü\"; 名前\n  x");
+    let tokens = lexer.lex_tokens().unwrap();
+    let positions: Vec<(&str, usize, usize)> = tokens
+      .iter()
+      .map(|t| (t.lexeme.as_str(), t.line, t.column))
+      .collect();
+    assert_eq!(
+      positions,
+      vec![
+        ("var", 0, 0),
+        ("é", 0, 4),
+        ("=", 0, 6),
+        ("\"ü\"", 0, 8),
+        (";", 0, 11),
+        ("名前", 0, 13),
+        ("x", 1, 2),
+        ("", 1, 3),
+      ]
+    );
   }
 }
@@ -0,0 +1,17 @@
+#![feature(vec_peek_mut)]
+#![feature(map_try_insert)]
+
+pub mod bignum;
+pub mod callable;
+pub mod environment;
+pub mod errors;
+pub mod interpreter;
+pub mod lexer;
+pub mod limits;
+pub mod lox;
+mod macros;
+pub mod modules;
+pub mod natives;
+pub mod parser;
+pub mod resolver;
+pub mod types;
@@ -0,0 +1,305 @@
+use crate::{
+  errors::RuntimeError,
+  types::{LoxMap, Object},
+};
+use std::{
+  any::Any,
+  cell::{Cell, RefCell},
+  collections::HashMap,
+  fmt, mem,
+  rc::{Rc, Weak},
+  time::{Duration, Instant},
+};
+
+/// each call into a lox function costs a handful of native stack frames, how many depends on the
+/// build and on how deeply the function nests blocks, so `DEFAULT_MAX_STACK` is the real guard
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 512;
+
+/// native stack a run may use, leaving room for the host below an 8MiB main thread. an interpreter
+/// run on a smaller thread needs a smaller limit
+pub const DEFAULT_MAX_STACK: usize = 6 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Limit {
+  Steps,
+  Time,
+  CallDepth,
+  Memory,
+}
+
+impl fmt::Display for Limit {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self {
+      Limit::Steps => write!(f, "step limit"),
+      Limit::Time => write!(f, "time limit"),
+      Limit::CallDepth => write!(f, "call depth limit"),
+      Limit::Memory => write!(f, "memory limit"),
+    }
+  }
+}
+
+/// execution budget for one `Interpreter::interpret` run, `None` means unlimited
+#[derive(Debug, Clone)]
+pub struct Limits {
+  pub max_steps: Option<u64>, // statements executed
+  pub timeout: Option<Duration>,
+  pub max_call_depth: Option<usize>,
+  pub max_stack: Option<usize>, // bytes of native stack, however deep the nesting that uses them
+  pub max_memory: Option<usize>, // bytes, as counted by `Heap`
+}
+
+impl Default for Limits {
+  fn default() -> Self {
+    Self {
+      max_steps: None,
+      timeout: None,
+      max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+      max_stack: Some(DEFAULT_MAX_STACK),
+      max_memory: None,
+    }
+  }
+}
+
+/// what's been charged to one list or map, handed back once nothing holds the collection
+#[derive(Debug)]
+struct Charge {
+  collection: Weak<dyn Any>, // also keeps the address from going to another collection
+  bytes: usize,
+}
+
+/// the address that tells a list or map apart and a handle that notices when it's gone
+fn identify(collection: &Object) -> Option<(*const (), Weak<dyn Any>)> {
+  let collection: Rc<dyn Any> = match collection {
+    Object::List(items) => Rc::clone(items) as Rc<dyn Any>,
+    Object::Map(map) => Rc::clone(map) as Rc<dyn Any>,
+    _ => return None,
+  };
+  Some((
+    Rc::as_ptr(&collection) as *const (),
+    Rc::downgrade(&collection),
+  ))
+}
+
+/// approximate bytes held by the values living in one interpreter's environments and the
+/// collections they point to, shared by all of them so an env going out of scope hands its bytes
+/// back. an env charges each value only for itself, what a list or map holds is charged to the
+/// collection once however many names or other collections refer to it
+#[derive(Debug, Default)]
+pub struct Heap {
+  used: Cell<usize>,
+  counting: Cell<bool>, // only while there's a memory limit
+  collections: RefCell<HashMap<*const (), Charge>>,
+}
+
+impl Heap {
+  pub fn used(&self) -> usize {
+    self.used.get()
+  }
+
+  pub fn set_counting(&self, counting: bool) {
+    self.counting.set(counting);
+  }
+
+  /// bytes to charge for holding `value`, nothing when no one is counting
+  pub fn size_of(&self, value: &Object) -> usize {
+    if self.counting.get() { value.size() } else { 0 }
+  }
+
+  /// bytes to charge for a list or map that was just made, the collection and its elements
+  fn size_of_collection(&self, collection: &Object) -> usize {
+    if !self.counting.get() {
+      return 0;
+    }
+    match collection {
+      Object::List(items) => {
+        mem::size_of::<RefCell<Vec<Object>>>()
+          + items.borrow().iter().map(Object::size).sum::<usize>()
+      }
+      Object::Map(map) => {
+        let entries: usize = map.borrow().iter().map(|(k, v)| k.size() + v.size()).sum();
+        mem::size_of::<RefCell<LoxMap>>() + entries
+      }
+      _ => 0,
+    }
+  }
+
+  pub fn alloc(&self, bytes: usize) {
+    self.used.set(self.used.get() + bytes);
+  }
+
+  pub fn free(&self, bytes: usize) {
+    self.used.set(self.used.get().saturating_sub(bytes));
+  }
+
+  /// charges `bytes` to `collection`, they're handed back by `discharge` or once it's gone
+  fn charge(&self, collection: &Object, bytes: usize) {
+    if !self.counting.get() {
+      return;
+    }
+    if let Some((id, handle)) = identify(collection) {
+      self
+        .collections
+        .borrow_mut()
+        .entry(id)
+        .or_insert(Charge {
+          collection: handle,
+          bytes: 0,
+        })
+        .bytes += bytes;
+      self.alloc(bytes);
+    }
+  }
+
+  /// hands back up to `bytes` of what was charged to `collection`
+  fn discharge(&self, collection: &Object, bytes: usize) {
+    let Some((id, _)) = identify(collection) else {
+      return;
+    };
+    if let Some(charge) = self.collections.borrow_mut().get_mut(&id) {
+      let bytes = bytes.min(charge.bytes);
+      charge.bytes -= bytes;
+      self.free(bytes);
+    }
+  }
+
+  /// hands back what was charged to the collections nothing holds anymore
+  fn sweep(&self) {
+    self.collections.borrow_mut().retain(|_, charge| {
+      let alive = charge.collection.strong_count() > 0;
+      if !alive {
+        self.free(charge.bytes);
+      }
+      alive
+    });
+  }
+}
+
+/// where the stack currently is, measured from a local in a frame of its own
+#[inline(never)]
+fn stack_position() -> usize {
+  let marker = 0u8;
+  std::hint::black_box(&marker) as *const u8 as usize
+}
+
+/// tracks how much of the `Limits` a run has used up
+#[derive(Debug, Default)]
+pub struct Budget {
+  limits: Limits,
+  heap: Rc<Heap>,
+  steps: u64,
+  call_depth: usize,
+  stack_base: usize, // stack position at the start of the run
+  deadline: Option<Instant>,
+}
+
+impl Budget {
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.heap.set_counting(limits.max_memory.is_some());
+    self.limits = limits;
+  }
+
+  pub fn heap(&self) -> Rc<Heap> {
+    Rc::clone(&self.heap)
+  }
+
+  /// resets the counters and starts the clock, called at the start of every run
+  pub fn start(&mut self) {
+    self.steps = 0;
+    self.call_depth = 0;
+    self.stack_base = stack_position();
+    self.deadline = self.limits.timeout.map(|t| Instant::now() + t);
+  }
+
+  pub fn step(&mut self) -> Result<(), RuntimeError> {
+    self.steps += 1;
+    if let Some(max) = self.limits.max_steps
+      && self.steps > max
+    {
+      return Err(RuntimeError::LimitExceeded(
+        Limit::Steps,
+        format!("executed more than {} statements", max),
+      ));
+    }
+
+    if let Some(deadline) = self.deadline
+      && Instant::now() >= deadline
+    {
+      return Err(RuntimeError::LimitExceeded(
+        Limit::Time,
+        format!(
+          "ran for longer than {:?}",
+          self.limits.timeout.unwrap_or_default()
+        ),
+      ));
+    }
+
+    // every level of nesting runs a statement, so this is where running out of stack is caught
+    if let Some(max) = self.limits.max_stack
+      && self.stack_base.abs_diff(stack_position()) > max
+    {
+      return Err(RuntimeError::LimitExceeded(
+        Limit::CallDepth,
+        format!("nested deep enough to use more than {} bytes of stack", max),
+      ));
+    }
+
+    self.reserve(0)
+  }
+
+  /// fails if holding `bytes` more than the heap currently does would go over the memory limit
+  pub fn reserve(&self, bytes: usize) -> Result<(), RuntimeError> {
+    let Some(max) = self.limits.max_memory else {
+      return Ok(());
+    };
+    // collections that went away are only noticed when it matters
+    if self.heap.used().saturating_add(bytes) > max {
+      self.heap.sweep();
+    }
+    if self.heap.used().saturating_add(bytes) > max {
+      return Err(RuntimeError::LimitExceeded(
+        Limit::Memory,
+        format!("holding more than {} bytes", max),
+      ));
+    }
+    Ok(())
+  }
+
+  /// charges the heap for a list or map that was just made, failing if that's over the limit
+  pub fn track(&self, collection: &Object) -> Result<(), RuntimeError> {
+    let bytes = self.heap.size_of_collection(collection);
+    self.reserve(bytes)?;
+    self.heap.charge(collection, bytes);
+    Ok(())
+  }
+
+  /// charges `collection` for `value` going into it, failing if that's over the limit
+  pub fn hold(&self, collection: &Object, value: &Object) -> Result<(), RuntimeError> {
+    let bytes = self.heap.size_of(value);
+    self.reserve(bytes)?;
+    self.heap.charge(collection, bytes);
+    Ok(())
+  }
+
+  /// hands back what `hold` charged for `value` once it's taken out of `collection`
+  pub fn release(&self, collection: &Object, value: &Object) {
+    self.heap.discharge(collection, self.heap.size_of(value));
+  }
+
+  pub fn enter_call(&mut self) -> Result<(), RuntimeError> {
+    if let Some(max) = self.limits.max_call_depth
+      && self.call_depth >= max
+    {
+      return Err(RuntimeError::LimitExceeded(
+        Limit::CallDepth,
+        format!("more than {} nested calls", max),
+      ));
+    }
+
+    self.call_depth += 1;
+    Ok(())
+  }
+
+  pub fn exit_call(&mut self) {
+    self.call_depth = self.call_depth.saturating_sub(1);
+  }
+}
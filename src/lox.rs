@@ -1,72 +1,624 @@
-use crate::{errors::LoxError, interpreter::Interpreter, lexer::Lexer, parser::Parser, resolver::{self, Resolver}};
+use crate::{
+  environment::Env,
+  errors::{LexError, LoxError, RuntimeError},
+  interpreter::Interpreter,
+  lexer::Lexer,
+  limits::Limits,
+  modules,
+  natives::Capabilities,
+  parser::Parser,
+  resolver::Resolver,
+  types::{Object, Stmt, TokenType},
+};
 use std::{
   fs,
-  io::{self, Write},
-  path::Path,
+  io::{self, BufRead, Write},
+  path::{Path, PathBuf},
+  rc::Rc,
 };
 
-pub struct Lox;
+pub struct Lox {
+  interpreter: Interpreter,
+}
 
 impl Lox {
-  pub fn new() -> Self {
-    Self {}
+  pub fn new(capabilities: Capabilities) -> Self {
+    Self::with_io(
+      capabilities,
+      Box::new(io::stdout()),
+      Box::new(io::stdout()),
+      Box::new(io::BufReader::new(io::stdin())),
+    )
   }
 
-  fn run(source: &str, repl: bool) -> Result<(), LoxError> {
-    let mut lexer = Lexer::new(source);
-    let tokens = match lexer.lex_tokens() {
-      Ok(tokens) => tokens,
-      Err(e) => {
-        LoxError::report(&LoxError::LexError(e.clone()));
-        return Err(LoxError::LexError(e));
-      }
-    };
+  /// program output, diagnostics and input all go through the given streams instead of stdio
+  pub fn with_io(
+    capabilities: Capabilities,
+    output: Box<dyn Write>,
+    diagnostics: Box<dyn Write>,
+    input: Box<dyn BufRead>,
+  ) -> Self {
+    Self {
+      interpreter: Interpreter::new(capabilities, output, diagnostics, input),
+    }
+  }
+
+  pub fn set_limits(&mut self, limits: Limits) {
+    self.interpreter.set_limits(limits);
+  }
+
+  /// makes `/`, `~/` and `%` by zero a runtime error instead of giving inf or NaN
+  pub fn set_strict_division(&mut self, strict: bool) {
+    self.interpreter.set_strict_division(strict);
+  }
+
+  /// makes int arithmetic that overflows wrap around instead of being a runtime error
+  pub fn set_wrapping_ints(&mut self, wrapping: bool) {
+    self.interpreter.set_wrapping_ints(wrapping);
+  }
 
-    let mut parser = Parser::new(tokens);
-    let statements = match parser.parse() {
+  /// directories searched, in order, for imports that aren't next to the importing file
+  pub fn set_search_path(&mut self, search_path: Vec<PathBuf>) {
+    self.interpreter.modules().set_search_path(search_path);
+  }
+
+  /// defines a global visible to every following run, e.g. the input a shared program runs on
+  pub fn define_global(&mut self, name: &str, value: &Object) {
+    self.interpreter.globals.borrow_mut().define(name, value);
+  }
+
+  pub fn global(&self, name: &str) -> Option<Object> {
+    Env::get_at(Rc::clone(&self.interpreter.globals), 0, name)
+  }
+
+  /// lexes and parses without running anything, the returned program can be run by any number of
+  /// independent `Lox` instances. every source gets its own file number, otherwise what the
+  /// resolver learned about one program would apply to same-placed names in the next
+  pub fn parse(&mut self, source: &str) -> Result<Rc<[Stmt]>, LoxError> {
+    let file = modules::next_file();
+    let mut parser = Parser::new(Lexer::new_in_file(source, file));
+    let parsed = parser.parse();
+    if let Some(e) = parser.lex_error() {
+      self.interpreter.report(&LoxError::LexError(e.clone()));
+      return Err(LoxError::LexError(e.clone()));
+    }
+
+    let statements = match parsed {
       Ok(statements) => statements,
       Err(e) => {
-        LoxError::report(&LoxError::ParseError(e.clone()));
+        for err in parser.errors() {
+          self.interpreter.report(&LoxError::ParseError(err.clone()));
+        }
+        self.interpreter.report(&LoxError::ParseError(e.clone()));
         return Err(LoxError::ParseError(e));
       }
     };
 
     // TODO: can print the statements here but need to implement an AstPrint for it
 
-    let mut interpreter = Interpreter::new();
+    Ok(statements.into())
+  }
 
-    let mut resolver = Resolver::new(&mut interpreter);
-    resolver.resolve_stmts(&statements);
+  /// resolves and runs an already parsed program against this instance's globals
+  pub fn run_program(&mut self, program: &[Stmt]) -> Result<(), LoxError> {
+    self.execute(program, false)
+  }
 
-    // TODO: don't interpret if there was an error
+  fn run(&mut self, source: &str, repl: bool) -> Result<(), LoxError> {
+    let program = self.parse(source)?;
+    self.execute(&program, repl)
+  }
 
-    interpreter.interpret(statements, repl);
+  fn execute(&mut self, statements: &[Stmt], repl: bool) -> Result<(), LoxError> {
+    let mut resolver = Resolver::new(&mut self.interpreter);
+    resolver.resolve_stmts(statements);
+    let errors = resolver.into_errors();
 
-    Ok(())
+    if !errors.is_empty() {
+      errors.iter().for_each(|e| self.interpreter.report(e));
+      return Err(LoxError::Error);
+    }
+
+    self
+      .interpreter
+      .interpret(statements, repl)
+      .map_err(LoxError::RuntimeError)
   }
 
-  pub fn run_file(&self, path: &str) -> Result<(), LoxError> {
+  pub fn run_file(&mut self, path: &str) -> Result<(), LoxError> {
     let source = fs::read_to_string(Path::new(path))?;
-    Self::run(&source, false)?;
+    self.interpreter.modules().set_main(Path::new(path));
+    self.run(&source, false)?;
     Ok(())
   }
 
-  // TODO: ctrl-c does nothing, ctrl-d quits
+  /// whether `source` stops inside a bracket, string or block comment, in which case the prompt
+  /// reads another line instead of reporting an error. any other lexing error is left to `run`
+  fn incomplete(source: &str) -> bool {
+    let mut depth = 0;
+    for token in Lexer::new(source) {
+      match token.map(|token| token.token_type) {
+        Ok(TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket) => depth += 1,
+        Ok(TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket) => depth -= 1,
+        Ok(_) => {}
+        Err(LexError::IncompleteString(..) | LexError::IncompleteComment(..)) => return true,
+        Err(_) => return false,
+      }
+    }
+
+    depth > 0
+  }
+
+  // TODO: ctrl-c does nothing
   // TODO: up and down arrow for history
   // TODO: left and right arrow for editing text
   pub fn run_prompt(&mut self) -> Result<(), LoxError> {
     loop {
       let mut input = String::new();
-      print!(">>> ");
-      io::stdout().flush().unwrap();
-      io::stdin().read_line(&mut input)?;
+      write!(self.interpreter.output(), ">>> ")?;
+      self.interpreter.output().flush()?;
+      if self.interpreter.input().read_line(&mut input)? == 0 {
+        return Ok(()); // ctrl-d
+      }
       if input.trim().is_empty() {
         continue;
       }
-      match Self::run(&input, true) {
+      while Self::incomplete(&input) {
+        write!(self.interpreter.output(), "... ")?;
+        self.interpreter.output().flush()?;
+        if self.interpreter.input().read_line(&mut input)? == 0 {
+          break; // ctrl-d runs what was typed so far
+        }
+      }
+      match self.run(&input, true) {
         Ok(_) => {}
+        Err(e @ LoxError::RuntimeError(RuntimeError::Exit(_))) => return Err(e),
         Err(_) => continue,
       }
     }
   }
 }
+
+#[cfg(test)]
+mod lox_tests {
+  use super::*;
+  use std::{cell::RefCell, fs, io, rc::Rc};
+
+  /// in memory sink that stays readable after being boxed into the interpreter
+  #[derive(Clone, Default)]
+  struct SharedBuf(Rc<RefCell<Vec<u8>>>);
+
+  impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  impl SharedBuf {
+    fn contents(&self) -> String {
+      String::from_utf8_lossy(&self.0.borrow())
+        .replace("\x1b[31m", "")
+        .replace("\x1b[0m", "")
+    }
+  }
+
+  fn run_with_limits(source: &str, input: &str, limits: Limits) -> String {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::Cursor::new(input.to_string())),
+    );
+    lox.set_limits(limits);
+    let _ = lox.run(source, false);
+    buf.contents()
+  }
+
+  fn run(source: &str, input: &str) -> String {
+    run_with_limits(source, input, Limits::default())
+  }
+
+  /// like `lox script`, so imports resolve next to the script
+  fn run_file_with_input(path: &Path, input: &str) -> String {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::MODULES,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::Cursor::new(input.to_string())),
+    );
+    let _ = lox.run_file(path.to_str().unwrap());
+    buf.contents()
+  }
+
+  #[test]
+  fn print_and_input_use_given_streams() {
+    let out = run(
+      "var name = input(); print \"hi \" + name; print input();",
+      "lox\n",
+    );
+    assert_eq!(out, "hi lox\nnone\n");
+  }
+
+  #[test]
+  fn runtime_errors_go_to_diagnostics() {
+    let out = SharedBuf::default();
+    let diagnostics = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(out.clone()),
+      Box::new(diagnostics.clone()),
+      Box::new(io::empty()),
+    );
+    let _ = lox.run("print 1; print -\"a\"; print 2;", false);
+
+    assert_eq!(out.contents(), "1\n2\n");
+    assert!(diagnostics.contents().contains("RuntimeError::InvalidType"));
+  }
+
+  #[test]
+  fn deeply_nested_collections_dont_overflow() {
+    // printed, compared and dropped with stacks of their own, this nests far deeper than the
+    // native stack of a test thread would allow recursing
+    let out = run(
+      "var a = []; var b = []; var m = {}; var n = {};
+      for (var i = 0; i < 20000; i = i + 1) { a = [a]; b = [b]; m = {\"k\": m}; n = {\"k\": n}; }
+      print len(\"${a}\"); print a == b; print m == n; b = [1]; print a == b;",
+      "",
+    );
+    assert_eq!(out, "40002\ntrue\ntrue\nfalse\n");
+
+    // and under a memory limit each level is charged once, not resized with every new level
+    let out = run_with_limits(
+      "var a = []; for (var i = 0; i < 20000; i = i + 1) { a = [a]; } print \"done\";",
+      "",
+      Limits {
+        max_memory: Some(100_000_000),
+        ..Limits::default()
+      },
+    );
+    assert_eq!(out, "done\n");
+  }
+
+  #[test]
+  fn exit_unwinds_to_the_host() {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PROCESS,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::empty()),
+    );
+    let result = lox.run(
+      "print 1; try { exit(3); } catch (e) { print \"caught\"; } finally { print 2; } print 4;",
+      false,
+    );
+
+    assert!(matches!(
+      result,
+      Err(LoxError::RuntimeError(RuntimeError::Exit(3)))
+    ));
+    // scripts can't catch exit, but finally blocks still run on the way out
+    assert_eq!(buf.contents(), "1\n2\n");
+    // and the host can carry on with the same instance
+    assert!(lox.run("print 5;", false).is_ok());
+    assert_eq!(buf.contents(), "1\n2\n5\n");
+  }
+
+  #[test]
+  fn limits_stop_the_run() {
+    let spin = "print 1; while (true) {} print 2;";
+    let out = run_with_limits(
+      spin,
+      "",
+      Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+      },
+    );
+    assert!(out.starts_with("1\n") && out.contains("step limit") && !out.contains("2\n"));
+
+    // scripts can't catch their way out of a limit
+    let out = run_with_limits(
+      "try { while (true) {} } catch (e) { print \"caught\"; }",
+      "",
+      Limits {
+        max_steps: Some(100),
+        ..Limits::default()
+      },
+    );
+    assert!(out.contains("step limit") && !out.contains("caught"));
+
+    let out = run_with_limits(
+      spin,
+      "",
+      Limits {
+        timeout: Some(std::time::Duration::from_millis(10)),
+        ..Limits::default()
+      },
+    );
+    assert!(out.contains("time limit"));
+
+    let recurse = "func f(n) { return f(n + 1); } f(0);";
+    let out = run_with_limits(
+      recurse,
+      "",
+      Limits {
+        max_call_depth: Some(50),
+        ..Limits::default()
+      },
+    );
+    assert!(out.contains("more than 50 nested calls"));
+
+    let grow = "var s = \"ab\"; while (true) { s = s + s; }";
+    let out = run_with_limits(
+      grow,
+      "",
+      Limits {
+        max_memory: Some(1 << 20),
+        ..Limits::default()
+      },
+    );
+    assert!(out.contains("memory limit"));
+
+    // what a list holds counts, not just its slots
+    let hoard = "var s = \"ab\"; for (var i = 0; i < 17; i = i + 1) { s = s + s; }
+      var xs = []; for (var i = 0; i < 2000; i = i + 1) { push(xs, s); } print \"done\";";
+    let out = run_with_limits(
+      hoard,
+      "",
+      Limits {
+        max_memory: Some(1_000_000),
+        ..Limits::default()
+      },
+    );
+    assert!(out.contains("memory limit") && !out.contains("done"));
+
+    // and is handed back once the list goes away
+    let churn = "var s = \"ab\"; for (var i = 0; i < 17; i = i + 1) { s = s + s; }
+      for (var i = 0; i < 2000; i = i + 1) { var xs = []; push(xs, s); push(xs, s); pop(xs); }
+      print \"done\";";
+    let out = run_with_limits(
+      churn,
+      "",
+      Limits {
+        max_memory: Some(1_000_000),
+        ..Limits::default()
+      },
+    );
+    assert!(out.contains("done"), "{}", out);
+
+    // a list is charged for what it holds once, however many names and lists refer to it and
+    // whichever of them goes away first, so a global list filled through a function local has
+    // room for as many strings as one they're pushed into directly
+    let held = |push: &str| {
+      let out = run_with_limits(
+        &format!(
+          "var s = \"ab\"; for (var i = 0; i < 15; i = i + 1) {{ s = s + s; }}
+          var kept = [];
+          func chunk() {{ var c = []; push(kept, c); push(c, s); }}
+          while (true) {{ {} print len(kept); }}",
+          push
+        ),
+        "",
+        Limits {
+          max_memory: Some(1_000_000),
+          ..Limits::default()
+        },
+      );
+      assert!(out.contains("memory limit"), "{}", out);
+      out
+        .lines()
+        .filter(|line| line.parse::<usize>().is_ok())
+        .count()
+    };
+    let direct = held("push(kept, s);");
+    assert!(direct >= 10, "{}", direct);
+    assert_eq!(held("chunk();"), direct);
+  }
+
+  #[test]
+  fn read_file_counts_against_memory() {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::FS_READ,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::empty()),
+    );
+    lox.set_limits(Limits {
+      max_memory: Some(1000),
+      ..Limits::default()
+    });
+    let _ = lox.run("print len(read_file(\"src/lox.rs\")) > 0;", false);
+    assert!(buf.contents().contains("memory limit") && !buf.contents().contains("true"));
+  }
+
+  #[test]
+  fn deep_nesting_stops_at_the_depth_limit() {
+    // nested blocks make each call use more stack than the call count alone accounts for
+    let scripts = [
+      "func f(n) { if (n > 0) { { { { { { f(n - 1); } } } } } } } f(500);",
+      "func f(n, step = 1) { if (n > 0) { { { { f(n - step); } } } } } f(600);",
+    ];
+    for script in scripts {
+      // the stack the interpreter gets on an 8MiB main thread, test threads are smaller
+      let out = std::thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || run(script, ""))
+        .unwrap()
+        .join()
+        .unwrap();
+      assert!(out.contains("call depth limit"), "{}", out);
+    }
+  }
+
+  #[test]
+  fn prompt_reads_on_until_input_is_complete() {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::Cursor::new(
+        "func add(a, b) {\n  return a + b;\n}\nprint add(1,\n 2);\nprint \"\"\"\n  two\n  lines\"\"\";\n",
+      )),
+    );
+    lox.run_prompt().unwrap();
+    assert_eq!(
+      buf.contents(),
+      ">>> ... ... >>> ... 3\n>>> ... ... two\nlines\n>>> "
+    );
+  }
+
+  #[test]
+  fn prompt_inputs_resolve_apart() {
+    // the second `a` sits where the first function's local did
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::Cursor::new(format!(
+        "func f() {{ var a = 1; {{ print a; }} }}\nvar a = 2;\n{:30}a;\nf();\n",
+        "print"
+      ))),
+    );
+    lox.run_prompt().unwrap();
+    assert_eq!(buf.contents(), ">>> >>> >>> 2\n>>> 1\nnone\n>>> ");
+  }
+
+  #[test]
+  fn sandbox_refuses_imports() {
+    let script = Path::new("test/modules/geometry.lox")
+      .canonicalize()
+      .unwrap();
+    let out = run(
+      &format!(
+        "import \"{}\" as g; try {{ import \"{}\" as h; }} catch (e) {{ print e[\"kind\"]; }}",
+        script.display(),
+        script.display()
+      ),
+      "",
+    );
+    assert!(out.contains("RuntimeError::PermissionDenied"), "{}", out);
+    assert!(out.ends_with("PermissionDenied\n"), "{}", out);
+  }
+
+  #[test]
+  fn strict_division_raises() {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::empty()),
+    );
+    lox.set_strict_division(true);
+    let _ = lox.run(
+      "print 1 / 2; try { print 1 % 0; } catch (e) { print e[\"kind\"]; }",
+      false,
+    );
+    assert_eq!(buf.contents(), "0.5\nDivisionByZero\n");
+  }
+
+  #[test]
+  fn wrapping_ints_wrap() {
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::empty()),
+    );
+    lox.set_wrapping_ints(true);
+    let _ = lox.run(
+      "print 9223372036854775807 + 1; print 3 << 62; print -(-9223372036854775807 - 1);",
+      false,
+    );
+    assert_eq!(
+      buf.contents(),
+      "-9223372036854775808\n-4611686018427387904\n-9223372036854775808\n"
+    );
+  }
+
+  #[test]
+  fn one_program_many_instances() {
+    let buf = SharedBuf::default();
+    let mut compiler = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::empty()),
+    );
+    let program = compiler
+      .parse("func double(x) { return x * 2; } var result = double(n);")
+      .unwrap();
+
+    let results: Vec<Object> = (0..3)
+      .map(|n| {
+        let mut lox = Lox::with_io(
+          Capabilities::PURE,
+          Box::new(buf.clone()),
+          Box::new(buf.clone()),
+          Box::new(io::empty()),
+        );
+        lox.define_global("n", &Object::Number(n as f64));
+        lox.run_program(&program).unwrap();
+        lox.global("result").unwrap()
+      })
+      .collect();
+
+    assert!(results == [0.0, 2.0, 4.0].map(Object::Number));
+    assert!(compiler.global("result").is_none());
+    assert_eq!(buf.contents(), "");
+  }
+
+  #[test]
+  fn programs_run_back_to_back() {
+    // `a` in the second program sits where the first program's local did
+    let buf = SharedBuf::default();
+    let mut lox = Lox::with_io(
+      Capabilities::PURE,
+      Box::new(buf.clone()),
+      Box::new(buf.clone()),
+      Box::new(io::empty()),
+    );
+    let first = lox.parse("func f() { var a = 1; { print a; } }").unwrap();
+    let second = lox.parse(&format!("{:30}a;", "var a = 2; print")).unwrap();
+    lox.run_program(&first).unwrap();
+    lox.run_program(&second).unwrap();
+    let third = lox.parse("f();").unwrap();
+    lox.run_program(&third).unwrap();
+    assert_eq!(buf.contents(), "2\n1\n");
+  }
+
+  /// every `test/<name>.lox` with a sibling `test/<name>.expected` must print exactly that
+  #[test]
+  fn golden_outputs() {
+    let mut checked = 0;
+    for entry in fs::read_dir("test").unwrap() {
+      let path = entry.unwrap().path();
+      let expected = path.with_extension("expected");
+      if path.extension().is_none_or(|ext| ext != "lox") || !expected.exists() {
+        continue;
+      }
+
+      let input = fs::read_to_string(path.with_extension("input")).unwrap_or_default();
+      assert_eq!(
+        run_file_with_input(&path, &input),
+        fs::read_to_string(&expected).unwrap(),
+        "output of {} differs from {}",
+        path.display(),
+        expected.display()
+      );
+      checked += 1;
+    }
+    assert!(checked > 0);
+  }
+}
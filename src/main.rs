@@ -1,33 +1,93 @@
-#![feature(vec_peek_mut)]
-#![feature(map_try_insert)]
-
-use crate::lox::Lox;
-use std::env;
-
-mod callable;
-mod environment;
-mod errors;
-mod interpreter;
-mod lexer;
-mod lox;
-mod macros;
-mod parser;
-mod resolver;
-mod types;
+use rlox::{
+  errors::{LoxError, RuntimeError},
+  limits::{DEFAULT_MAX_STACK, Limits},
+  lox::Lox,
+  natives::Capabilities,
+};
+use std::{env, path::PathBuf, process, thread, time::Duration};
+
+/// stack left over for main and the parser on top of what the interpreter may use
+const STACK_HEADROOM: usize = 2 * 1024 * 1024;
 
 // TODO: implement classes (although I only want structs)
 
+const USAGE: &str = "usage: lox [--max-steps n] [--timeout ms] [--max-depth n] [--max-stack bytes] [--max-memory bytes] [--sandbox] [--strict-division] [--wrap-ints] [--path dir]... [script], or lox (for repl)";
+
 fn main() {
-  let args: Vec<String> = env::args().collect();
+  let mut args = env::args().skip(1);
+  let mut limits = Limits::default();
+  let mut capabilities = Capabilities::ALL;
+  let mut strict_division = false;
+  let mut wrapping_ints = false;
+  let mut search_path = vec![];
+  let mut script = None;
+
+  while let Some(arg) = args.next() {
+    let mut value = || -> u64 {
+      match args.next().and_then(|v| v.parse().ok()) {
+        Some(v) => v,
+        None => {
+          println!("{}", USAGE);
+          process::exit(64);
+        }
+      }
+    };
+
+    match arg.as_str() {
+      "--max-steps" => limits.max_steps = Some(value()),
+      "--timeout" => limits.timeout = Some(Duration::from_millis(value())),
+      "--max-depth" => limits.max_call_depth = Some(value() as usize),
+      "--max-stack" => limits.max_stack = Some(value() as usize),
+      "--max-memory" => limits.max_memory = Some(value() as usize),
+      "--sandbox" => capabilities = Capabilities::PURE,
+      "--strict-division" => strict_division = true,
+      "--wrap-ints" => wrapping_ints = true,
+      "--path" => match args.next() {
+        Some(dir) => search_path.push(PathBuf::from(dir)),
+        None => {
+          println!("{}", USAGE);
+          process::exit(64);
+        }
+      },
+      _ if script.is_none() && !arg.starts_with("--") => script = Some(arg),
+      _ => {
+        println!("{}", USAGE);
+        return;
+      }
+    }
+  }
 
-  let mut lox = Lox::new();
+  // the interpreter runs on a thread with room for its stack limit, whatever the main thread got
+  let stack_size = limits.max_stack.unwrap_or(DEFAULT_MAX_STACK) + STACK_HEADROOM;
+  let interpreter = thread::Builder::new()
+    .stack_size(stack_size)
+    .spawn(move || {
+      let mut lox = Lox::new(capabilities);
+      lox.set_limits(limits);
+      lox.set_search_path(search_path);
+      lox.set_strict_division(strict_division);
+      lox.set_wrapping_ints(wrapping_ints);
 
-  if args.len() > 2 {
-    println!("usage: lox [script], or lox (for repl)");
-    return;
-  } else if args.len() == 2 {
-    lox.run_file(&args[1]).unwrap();
-  } else {
-    lox.run_prompt().unwrap();
+      if let Some(script) = script {
+        match lox.run_file(&script) {
+          Ok(_) => 0,
+          Err(LoxError::RuntimeError(RuntimeError::Exit(status))) => status,
+          // errors have already been reported through the interpreter's diagnostics stream
+          Err(_) => 65,
+        }
+      } else {
+        match lox.run_prompt() {
+          Err(LoxError::RuntimeError(RuntimeError::Exit(status))) => status,
+          result => {
+            result.unwrap();
+            0
+          }
+        }
+      }
+    })
+    .unwrap();
+  let status = interpreter.join().unwrap();
+  if status != 0 {
+    process::exit(status);
   }
 }
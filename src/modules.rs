@@ -0,0 +1,104 @@
+use crate::{environment::Env, errors::RuntimeError, types::Token};
+use std::{
+  cell::RefCell,
+  collections::HashMap,
+  fmt,
+  path::{Path, PathBuf},
+  rc::Rc,
+  sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// file numbers handed out so far by every loader, a parsed program can run on any instance so
+/// its number must be unique to the process
+static FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// a file number no other source has had, so tokens placed alike in two sources stay apart
+pub fn next_file() -> usize {
+  FILES.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// an imported file, its globals live as long as anything still refers to the module
+pub struct Module {
+  pub name: String,
+  pub globals: Rc<RefCell<Env>>,
+}
+
+impl fmt::Display for Module {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "<module {}>", self.name)
+  }
+}
+
+/// finds imported files and remembers which ones already ran
+#[derive(Default)]
+pub struct ModuleLoader {
+  search_path: Vec<PathBuf>,
+  main: Option<PathBuf>, // the script being run, if it came from a file
+  loading: Vec<PathBuf>, // imports currently being run, innermost last
+  cache: HashMap<PathBuf, Rc<Module>>,
+}
+
+impl ModuleLoader {
+  /// directories searched, in order, for imports not found next to the importing file
+  pub fn set_search_path(&mut self, search_path: Vec<PathBuf>) {
+    self.search_path = search_path;
+  }
+
+  pub fn set_main(&mut self, path: &Path) {
+    self.main = path.canonicalize().ok();
+  }
+
+  /// the canonical path `import` refers to from the file currently running
+  pub fn locate(&self, import: &Token) -> Result<PathBuf, RuntimeError> {
+    let relative = Path::new(import.literal.to_str().unwrap_or_default().as_str()).to_owned();
+    let importer_dir = self
+      .loading
+      .last()
+      .or(self.main.as_ref())
+      .and_then(|file| file.parent())
+      .unwrap_or(Path::new("."));
+
+    std::iter::once(importer_dir)
+      .chain(self.search_path.iter().map(PathBuf::as_path))
+      .find_map(|dir| dir.join(&relative).canonicalize().ok())
+      .ok_or_else(|| {
+        RuntimeError::ImportFailed(
+          import.line,
+          import.lexeme.clone(),
+          "no such file next to the importing file or on the search path".to_string(),
+        )
+      })
+  }
+
+  pub fn cached(&self, file: &Path) -> Option<Rc<Module>> {
+    self.cache.get(file).cloned()
+  }
+
+  /// marks `file` as running, failing if that would close a cycle of imports; returns the file
+  /// number its tokens get
+  pub fn enter(&mut self, file: &Path, import: &Token) -> Result<usize, RuntimeError> {
+    let chain: Vec<&PathBuf> = self.main.iter().chain(self.loading.iter()).collect();
+    if let Some(start) = chain.iter().position(|f| *f == file) {
+      let cycle: Vec<String> = chain[start..]
+        .iter()
+        .map(|f| f.display().to_string())
+        .chain(std::iter::once(file.display().to_string()))
+        .collect();
+      return Err(RuntimeError::ImportFailed(
+        import.line,
+        import.lexeme.clone(),
+        format!("cyclic import: {}", cycle.join(" -> ")),
+      ));
+    }
+
+    self.loading.push(file.to_owned());
+    Ok(next_file())
+  }
+
+  /// `file` is done running, a finished module is cached so it never runs again
+  pub fn exit(&mut self, module: Option<Rc<Module>>) {
+    if let (Some(file), Some(module)) = (self.loading.pop(), module) {
+      self.cache.insert(file, module);
+    }
+  }
+}
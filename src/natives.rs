@@ -0,0 +1,526 @@
+use crate::{
+  bignum::{BigInt, Decimal},
+  callable::{Arity, Callable},
+  environment::Env,
+  errors::RuntimeError,
+  interpreter::{Interpreter, normalize_index},
+  types::{LoxMap, Object},
+};
+use std::{
+  cell::{Cell, RefCell},
+  env, fmt, fs, ops,
+  rc::Rc,
+  time,
+};
+
+/// what the host lets a script touch, natives needing anything missing are never defined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities(u8);
+
+impl Capabilities {
+  pub const PURE: Self = Self(0);
+  pub const TIME: Self = Self(1 << 0);
+  pub const FS_READ: Self = Self(1 << 1);
+  pub const FS_WRITE: Self = Self(1 << 2);
+  pub const ENV: Self = Self(1 << 3);
+  pub const PROCESS: Self = Self(1 << 4);
+  pub const MODULES: Self = Self(1 << 5); // importing other files, which reads them from disk
+  pub const ALL: Self = Self(0b111111);
+
+  pub fn contains(self, other: Self) -> bool {
+    self.0 & other.0 == other.0
+  }
+}
+
+impl ops::BitOr for Capabilities {
+  type Output = Self;
+
+  fn bitor(self, rhs: Self) -> Self {
+    Self(self.0 | rhs.0)
+  }
+}
+
+type NativeBody = fn(&mut Interpreter, &[Object]) -> Result<Object, RuntimeError>;
+
+#[derive(Clone, Copy)]
+pub struct NativeFn {
+  name: &'static str,
+  arity: Arity,
+  requires: Capabilities,
+  body: NativeBody,
+}
+
+impl fmt::Display for NativeFn {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "<fn> {}", self.name)
+  }
+}
+
+impl Callable for NativeFn {
+  fn call(
+    &self,
+    interpreter: &mut Interpreter,
+    arguments: &[Object],
+  ) -> Result<Object, RuntimeError> {
+    (self.body)(interpreter, arguments)
+  }
+
+  fn arity(&self) -> Arity {
+    self.arity
+  }
+}
+
+const NATIVES: &[NativeFn] = &[
+  NativeFn {
+    name: "input",
+    arity: Arity::exactly(0),
+    requires: Capabilities::PURE, // reads the host supplied input stream, not stdin
+    body: input,
+  },
+  NativeFn {
+    name: "len",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PURE,
+    body: len,
+  },
+  NativeFn {
+    name: "push",
+    arity: Arity::exactly(2),
+    requires: Capabilities::PURE,
+    body: push,
+  },
+  NativeFn {
+    name: "pop",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PURE,
+    body: pop,
+  },
+  NativeFn {
+    name: "insert",
+    arity: Arity::exactly(3),
+    requires: Capabilities::PURE,
+    body: insert,
+  },
+  NativeFn {
+    name: "remove",
+    arity: Arity::exactly(2),
+    requires: Capabilities::PURE,
+    body: remove,
+  },
+  NativeFn {
+    name: "has",
+    arity: Arity::exactly(2),
+    requires: Capabilities::PURE,
+    body: has,
+  },
+  NativeFn {
+    name: "keys",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PURE,
+    body: keys,
+  },
+  NativeFn {
+    name: "values",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PURE,
+    body: values,
+  },
+  NativeFn {
+    name: "range",
+    arity: Arity::exactly(2),
+    requires: Capabilities::PURE,
+    body: range,
+  },
+  NativeFn {
+    name: "format",
+    arity: Arity::at_least(1),
+    requires: Capabilities::PURE,
+    body: format,
+  },
+  NativeFn {
+    name: "bigint",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PURE,
+    body: bigint,
+  },
+  NativeFn {
+    name: "decimal",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PURE,
+    body: decimal,
+  },
+  NativeFn {
+    name: "clock",
+    arity: Arity::exactly(0),
+    requires: Capabilities::TIME,
+    body: clock,
+  },
+  NativeFn {
+    name: "read_file",
+    arity: Arity::exactly(1),
+    requires: Capabilities::FS_READ,
+    body: read_file,
+  },
+  NativeFn {
+    name: "write_file",
+    arity: Arity::exactly(2),
+    requires: Capabilities::FS_WRITE,
+    body: write_file,
+  },
+  NativeFn {
+    name: "getenv",
+    arity: Arity::exactly(1),
+    requires: Capabilities::ENV,
+    body: getenv,
+  },
+  NativeFn {
+    name: "exit",
+    arity: Arity::exactly(1),
+    requires: Capabilities::PROCESS,
+    body: exit,
+  },
+];
+
+/// the iterator `range(start, stop)` returns, counts up by one until it reaches `stop`
+struct RangeIter {
+  next: Cell<i64>,
+  stop: i64,
+}
+
+impl fmt::Display for RangeIter {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "<iterator> range")
+  }
+}
+
+impl Callable for RangeIter {
+  fn call(
+    &self,
+    _interpreter: &mut Interpreter,
+    _arguments: &[Object],
+  ) -> Result<Object, RuntimeError> {
+    let next = self.next.get();
+    if next >= self.stop {
+      return Ok(Object::None);
+    }
+    self.next.set(next + 1);
+    Ok(Object::Int(next))
+  }
+
+  fn arity(&self) -> Arity {
+    Arity::exactly(0)
+  }
+}
+
+/// defines every native the capabilities allow in `globals`
+pub fn define_natives(globals: &mut Env, capabilities: Capabilities) {
+  for native in NATIVES {
+    if capabilities.contains(native.requires) {
+      globals.define(native.name, &Object::Callable(Rc::new(*native)));
+    }
+  }
+}
+
+fn native_error(name: &str, msg: impl fmt::Display) -> RuntimeError {
+  RuntimeError::NativeError(name.to_string(), msg.to_string())
+}
+
+fn string_arg(name: &str, arg: &Object) -> Result<String, RuntimeError> {
+  arg
+    .to_str()
+    .ok_or_else(|| native_error(name, format!("expected a string, got {}", arg)))
+}
+
+fn list_arg(name: &str, arg: &Object) -> Result<Rc<RefCell<Vec<Object>>>, RuntimeError> {
+  match arg {
+    Object::List(items) => Ok(Rc::clone(items)),
+    _ => Err(native_error(
+      name,
+      format!("expected a list, got {}", arg.repr()),
+    )),
+  }
+}
+
+fn map_arg(name: &str, arg: &Object) -> Result<Rc<RefCell<LoxMap>>, RuntimeError> {
+  match arg {
+    Object::Map(map) => Ok(Rc::clone(map)),
+    _ => Err(native_error(
+      name,
+      format!("expected a map, got {}", arg.repr()),
+    )),
+  }
+}
+
+fn key_arg<'a>(name: &str, arg: &'a Object) -> Result<&'a Object, RuntimeError> {
+  if arg.is_hashable() {
+    Ok(arg)
+  } else {
+    Err(native_error(
+      name,
+      format!("{} can't be used as a map key", arg.repr()),
+    ))
+  }
+}
+
+fn index_arg(name: &str, arg: &Object, len: usize) -> Result<usize, RuntimeError> {
+  let Some(n) = arg.to_int() else {
+    return Err(native_error(
+      name,
+      format!("expected an integer index, got {}", arg.repr()),
+    ));
+  };
+
+  normalize_index(n, len).ok_or_else(|| {
+    native_error(
+      name,
+      format!("index {} is out of bounds for length {}", n, len),
+    )
+  })
+}
+
+fn len(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  match &arguments[0] {
+    Object::List(items) => Ok(Object::Int(items.borrow().len() as i64)),
+    Object::Map(map) => Ok(Object::Int(map.borrow().len() as i64)),
+    Object::String(s) => Ok(Object::Int(s.chars().count() as i64)),
+    other => Err(native_error(
+      "len",
+      format!("expected a list, map or string, got {}", other.repr()),
+    )),
+  }
+}
+
+fn push(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let items = list_arg("push", &arguments[0])?;
+  interpreter.hold(&arguments[0], &arguments[1])?;
+  items.borrow_mut().push(arguments[1].clone());
+  Ok(Object::None)
+}
+
+fn pop(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let items = list_arg("pop", &arguments[0])?;
+  let popped = items.borrow_mut().pop();
+  let popped = popped.ok_or_else(|| native_error("pop", "pop from an empty list"))?;
+  interpreter.release(&arguments[0], &popped);
+  Ok(popped)
+}
+
+fn insert(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let items = list_arg("insert", &arguments[0])?;
+  let len = items.borrow().len();
+  // inserting at len appends, negative indexes still count from the last element
+  let i = match arguments[1].to_int() {
+    Some(n) if n == len as i64 => len,
+    _ => index_arg("insert", &arguments[1], len)?,
+  };
+  interpreter.hold(&arguments[0], &arguments[2])?;
+  items.borrow_mut().insert(i, arguments[2].clone());
+  Ok(Object::None)
+}
+
+/// removes by index from a list or by key from a map, returning the removed value
+fn remove(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  if let Object::Map(map) = &arguments[0] {
+    let key = key_arg("remove", &arguments[1])?;
+    let removed = map.borrow_mut().remove(key);
+    let removed =
+      removed.ok_or_else(|| native_error("remove", format!("no key {} in map", key.repr())))?;
+    interpreter.release(&arguments[0], key);
+    interpreter.release(&arguments[0], &removed);
+    return Ok(removed);
+  }
+
+  let items = list_arg("remove", &arguments[0])?;
+  let len = items.borrow().len();
+  let i = index_arg("remove", &arguments[1], len)?;
+  let removed = items.borrow_mut().remove(i);
+  interpreter.release(&arguments[0], &removed);
+  Ok(removed)
+}
+
+fn has(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let map = map_arg("has", &arguments[0])?;
+  let key = key_arg("has", &arguments[1])?;
+  Ok(Object::Bool(map.borrow().contains_key(key)))
+}
+
+fn keys(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let map = map_arg("keys", &arguments[0])?;
+  let keys = Object::new_list(map.borrow().keys().cloned().collect());
+  interpreter.track(&keys)?;
+  Ok(keys)
+}
+
+fn values(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let map = map_arg("values", &arguments[0])?;
+  let values = Object::new_list(map.borrow().values().cloned().collect());
+  interpreter.track(&values)?;
+  Ok(values)
+}
+
+fn range(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  match (&arguments[0], &arguments[1]) {
+    (Object::Int(start), Object::Int(stop)) => Ok(Object::Callable(Rc::new(RangeIter {
+      next: Cell::new(*start),
+      stop: *stop,
+    }))),
+    _ => Err(native_error("range", "expects two integers")),
+  }
+}
+
+/// fills each `{}` in the template with the next argument, `{{` and `}}` stand for the braces
+fn format(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let template = string_arg("format", &arguments[0])?;
+  let mut values = arguments[1..].iter();
+  let mut result = String::with_capacity(template.len());
+  let mut chars = template.chars().peekable();
+  while let Some(c) = chars.next() {
+    match (c, chars.peek()) {
+      ('{', Some('{')) | ('}', Some('}')) => {
+        chars.next();
+        result.push(c);
+      }
+      ('{', Some('}')) => {
+        chars.next();
+        let value = values
+          .next()
+          .ok_or_else(|| native_error("format", "more placeholders than values"))?;
+        result.push_str(&value.to_string());
+      }
+      _ => result.push(c),
+    }
+  }
+
+  if values.next().is_some() {
+    return Err(native_error("format", "more values than placeholders"));
+  }
+  interpreter.reserve(result.len())?;
+  Ok(Object::String(result))
+}
+
+/// converts an int, a whole float or decimal, or a string of digits to a bigint
+fn bigint(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let value = match &arguments[0] {
+    Object::String(s) => BigInt::parse(s.trim(), 10),
+    Object::Number(n) => Decimal::from_f64(*n)
+      .filter(Decimal::is_integral)
+      .map(|n| n.trunc()),
+    Object::Decimal(n) => n.is_integral().then(|| n.trunc()),
+    other => other.to_bigint(),
+  };
+
+  value.map(|n| Object::BigInt(Rc::new(n))).ok_or_else(|| {
+    native_error(
+      "bigint",
+      format!("can't convert {} to a bigint", arguments[0].repr()),
+    )
+  })
+}
+
+/// converts a number or a string like "1.10" to a decimal. a float converts as it prints, so
+/// decimal(0.1) is 0.1 rather than the binary fraction closest to it
+fn decimal(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let value = match &arguments[0] {
+    Object::String(s) => Decimal::parse(s.trim()),
+    Object::Number(n) if n.is_finite() => Decimal::parse(&format!("{:?}", n)),
+    other => other.to_decimal(),
+  };
+
+  value.map(|n| Object::Decimal(Rc::new(n))).ok_or_else(|| {
+    native_error(
+      "decimal",
+      format!("can't convert {} to a decimal", arguments[0].repr()),
+    )
+  })
+}
+
+/// reads one line from the interpreter's input, none once the input is exhausted
+fn input(interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object, RuntimeError> {
+  interpreter.output().flush()?;
+
+  let mut line = String::new();
+  if interpreter.input().read_line(&mut line)? == 0 {
+    return Ok(Object::None);
+  }
+
+  Ok(Object::String(
+    line.trim_end_matches(['\n', '\r']).to_string(),
+  ))
+}
+
+fn clock(_interpreter: &mut Interpreter, _arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let now = time::SystemTime::now()
+    .duration_since(time::UNIX_EPOCH)
+    .map_err(|e| native_error("clock", e))?;
+
+  Ok(Object::Number(now.as_secs_f64()))
+}
+
+fn read_file(interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let path = string_arg("read_file", &arguments[0])?;
+  // checked before reading as well, so a huge file isn't read only to be refused
+  if let Ok(metadata) = fs::metadata(&path) {
+    interpreter.reserve(metadata.len() as usize)?;
+  }
+  match fs::read_to_string(&path) {
+    Ok(contents) => {
+      interpreter.reserve(contents.len())?;
+      Ok(Object::String(contents))
+    }
+    Err(e) => Err(native_error("read_file", format!("{}: {}", path, e))),
+  }
+}
+
+fn write_file(
+  _interpreter: &mut Interpreter,
+  arguments: &[Object],
+) -> Result<Object, RuntimeError> {
+  let path = string_arg("write_file", &arguments[0])?;
+  match fs::write(&path, arguments[1].to_string()) {
+    Ok(_) => Ok(Object::None),
+    Err(e) => Err(native_error("write_file", format!("{}: {}", path, e))),
+  }
+}
+
+fn getenv(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let name = string_arg("getenv", &arguments[0])?;
+  Ok(env::var(name).map_or(Object::None, Object::String))
+}
+
+/// unwinds the whole run, it's up to whoever started it what exiting means
+fn exit(_interpreter: &mut Interpreter, arguments: &[Object]) -> Result<Object, RuntimeError> {
+  let code = arguments[0]
+    .to_num()
+    .ok_or_else(|| native_error("exit", format!("expected a number, got {}", arguments[0])))?;
+
+  Err(RuntimeError::Exit(code as i32))
+}
+
+#[cfg(test)]
+mod natives_tests {
+  use super::*;
+  use crate::types::{Token, TokenType};
+
+  fn defined(capabilities: Capabilities, name: &str) -> bool {
+    let mut globals = Env::default();
+    define_natives(&mut globals, capabilities);
+    let name = Token::new(TokenType::Identifier, name, Object::None, 0, 0);
+    globals.get(&name).is_ok()
+  }
+
+  #[test]
+  fn natives_follow_capabilities() {
+    assert!(defined(Capabilities::PURE, "input"));
+    assert!(!defined(Capabilities::PURE, "clock"));
+    assert!(defined(Capabilities::TIME, "clock"));
+    assert!(defined(
+      Capabilities::TIME | Capabilities::FS_READ,
+      "read_file"
+    ));
+    assert!(!defined(
+      Capabilities::TIME | Capabilities::FS_READ,
+      "write_file"
+    ));
+    assert!(defined(Capabilities::ALL, "exit"));
+  }
+}
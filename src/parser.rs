@@ -1,22 +1,42 @@
 use crate::{
-  errors::{LoxError, ParseError},
-  types::{Expr, Object, Stmt, Token, TokenType},
+  errors::{LexError, ParseError},
+  types::{Expr, Object, Param, Stmt, Token, TokenType},
 };
-
-pub struct Parser {
-  tokens: Vec<Token>,
-  current: usize,
-  had_error: bool,
+use std::collections::VecDeque;
+
+/// pulls tokens from the lexer only as far as it needs to look ahead
+pub struct Parser<I: Iterator<Item = Result<Token, LexError>>> {
+  tokens: I,
+  lookahead: VecDeque<(Token, Option<String>)>, // upcoming tokens and the doc comment before each
+  previous: Option<Token>,
+  doc: Option<String>, // doc comment lines read ahead of the token they belong to
+  errors: Vec<ParseError>,
+  lex_error: Option<LexError>,
 }
 
 // TODO: write a cool visualizer for this
-impl Parser {
-  pub fn new(tokens: &Vec<Token>) -> Self {
-    Self {
-      tokens: tokens.to_vec(),
-      current: 0,
-      had_error: false,
-    }
+impl<I: Iterator<Item = Result<Token, LexError>>> Parser<I> {
+  pub fn new(tokens: I) -> Self {
+    let mut parser = Self {
+      tokens,
+      lookahead: VecDeque::new(),
+      previous: None,
+      doc: None,
+      errors: vec![],
+      lex_error: None,
+    };
+    parser.fill(1);
+    parser
+  }
+
+  /// the error that cut the token stream short, if any. parsing stops there as if the input ended
+  pub fn lex_error(&self) -> Option<&LexError> {
+    self.lex_error.as_ref()
+  }
+
+  /// every error hit while parsing, in the order they occured
+  pub fn errors(&self) -> &[ParseError] {
+    &self.errors
   }
 
   pub fn parse(&mut self) -> Result<Vec<Stmt>, ParseError> {
@@ -25,14 +45,13 @@ impl Parser {
       match self.declaration() {
         Ok(val) => statements.push(val),
         Err(e) => {
-          self.had_error = true;
+          self.errors.push(e);
           self.synchronize();
-          LoxError::report(&LoxError::ParseError(e));
         }
       }
     }
 
-    if self.had_error {
+    if !self.errors.is_empty() {
       Err(ParseError::Error(
         "one or more parsing errors have occured".to_string(),
       ))
@@ -47,10 +66,17 @@ impl Parser {
   }
 
   fn declaration(&mut self) -> Result<Stmt, ParseError> {
-    if self.amatch(&[TokenType::Func]) {
-      self.function("function")
-    } else if self.amatch(&[TokenType::Var]) {
-      self.var_declaration()
+    // a doc comment before anything other than a function or variable is dropped
+    let doc = self.lookahead[0].1.take();
+
+    // without a name 'func' starts a lambda expression instead
+    if self.check(&TokenType::Func) && self.check_ahead(1, &TokenType::Identifier) {
+      self.advance();
+      self.function("function", doc)
+    } else if self.amatch(&[TokenType::Var, TokenType::Const]) {
+      self.var_declaration(doc)
+    } else if self.amatch(&[TokenType::Import, TokenType::From]) {
+      self.import_declaration()
     } else {
       self.statement()
     }
@@ -58,8 +84,10 @@ impl Parser {
   }
 
   fn statement(&mut self) -> Result<Stmt, ParseError> {
-    if self.amatch(&[TokenType::For]) {
-      self.for_statement()
+    if self.check(&TokenType::Identifier) && self.check_ahead(1, &TokenType::Colon) {
+      self.labeled_statement()
+    } else if self.amatch(&[TokenType::For]) {
+      self.for_statement(None)
     } else if self.amatch(&[TokenType::If]) {
       self.if_statement()
     } else if self.amatch(&[TokenType::Print]) {
@@ -67,7 +95,13 @@ impl Parser {
     } else if self.amatch(&[TokenType::Return]) {
       self.return_stmt()
     } else if self.amatch(&[TokenType::While]) {
-      self.while_statement()
+      self.while_statement(None)
+    } else if self.amatch(&[TokenType::Break, TokenType::Continue]) {
+      self.jump_statement()
+    } else if self.amatch(&[TokenType::Throw]) {
+      self.throw_statement()
+    } else if self.amatch(&[TokenType::Try]) {
+      self.try_statement()
     } else if self.amatch(&[TokenType::LeftBrace]) {
       Ok(Stmt::Block {
         statements: self.block()?,
@@ -77,14 +111,97 @@ impl Parser {
     }
   }
 
-  fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+  /// labeled        → IDENTIFIER ":" ( forStmt | whileStmt ) ;
+  fn labeled_statement(&mut self) -> Result<Stmt, ParseError> {
+    let label = self.advance();
+    self.advance(); // the ':'
+
+    if self.amatch(&[TokenType::For]) {
+      self.for_statement(Some(label))
+    } else if self.amatch(&[TokenType::While]) {
+      self.while_statement(Some(label))
+    } else {
+      Err(ParseError::EndOfExpression(format!(
+        "expect a loop after label '{}'",
+        label.lexeme
+      )))
+    }
+  }
+
+  /// jumpStmt       → ( "break" | "continue" ) IDENTIFIER? ";" ;
+  fn jump_statement(&mut self) -> Result<Stmt, ParseError> {
+    let keyword = self.previous();
+    let label = if self.check(&TokenType::Identifier) {
+      Some(self.advance())
+    } else {
+      None
+    };
+    self.consume(
+      &TokenType::Semicolon,
+      &format!("expect ';' after '{}'", keyword.lexeme),
+    )?;
+
+    Ok(match keyword.token_type {
+      TokenType::Break => Stmt::Break { keyword, label },
+      _ => Stmt::Continue { keyword, label },
+    })
+  }
+
+  fn throw_statement(&mut self) -> Result<Stmt, ParseError> {
+    let keyword = self.previous();
+    let value = self.expression()?;
+    self.consume(&TokenType::Semicolon, "expect ';' after thrown value")?;
+    Ok(Stmt::Throw { keyword, value })
+  }
+
+  /// tryStmt        → "try" block ( "catch" "(" IDENTIFIER ")" block )? ( "finally" block )? ;
+  fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+    self.consume(&TokenType::LeftBrace, "expect '{' after 'try'")?;
+    let body = self.block()?;
+
+    let mut catch = None;
+    if self.amatch(&[TokenType::Catch]) {
+      self.consume(&TokenType::LeftParen, "expect '(' after 'catch'")?;
+      let name = self.consume(&TokenType::Identifier, "expect caught error name")?;
+      self.consume(&TokenType::RightParen, "expect ')' after caught error name")?;
+      self.consume(&TokenType::LeftBrace, "expect '{' before catch body")?;
+      catch = Some((name, self.block()?));
+    }
+
+    let mut finally = None;
+    if self.amatch(&[TokenType::Finally]) {
+      self.consume(&TokenType::LeftBrace, "expect '{' after 'finally'")?;
+      finally = Some(self.block()?);
+    }
+
+    if catch.is_none() && finally.is_none() {
+      return Err(ParseError::EndOfExpression(
+        "expect 'catch' or 'finally' after try block".to_string(),
+      ));
+    }
+
+    Ok(Stmt::Try {
+      body,
+      catch,
+      finally,
+    })
+  }
+
+  fn for_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParseError> {
     self.consume(&TokenType::LeftParen, "expect '(' after 'for'")?;
 
+    if self.check(&TokenType::Var)
+      && self.check_ahead(1, &TokenType::Identifier)
+      && self.check_ahead(2, &TokenType::In)
+    {
+      return self.for_in_statement(label);
+    }
+
     let mut initializer = None;
     if self.amatch(&[TokenType::Semicolon]) {
       initializer = None;
     } else if self.amatch(&[TokenType::Var]) {
-      initializer = Some(self.var_declaration()?);
+      initializer = Some(self.var_declaration(None)?);
     } else {
       initializer = Some(self.expr_stmt()?);
     }
@@ -101,19 +218,15 @@ impl Parser {
     }
     self.consume(&TokenType::RightParen, "expect ')' after for clauses")?;
 
-    let mut body = self.statement()?;
-
-    if let Some(inc) = increment {
-      body = Stmt::Block {
-        statements: vec![body, Stmt::Expression { expression: inc }],
-      };
-    }
+    let body = self.statement()?;
 
-    body = Stmt::While {
+    let mut body = Stmt::While {
       condition: condition.unwrap_or(Expr::Literal {
         value: Object::Bool(true),
       }),
       body: Box::new(body),
+      increment,
+      label,
     };
 
     if let Some(init) = initializer {
@@ -125,7 +238,24 @@ impl Parser {
     Ok(body)
   }
 
-  fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+  /// forIn          → "for" "(" "var" IDENTIFIER "in" expression ")" statement ;
+  fn for_in_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParseError> {
+    self.consume(&TokenType::Var, "expect 'var' in for-in loop")?;
+    let name = self.consume(&TokenType::Identifier, "expect loop variable name")?;
+    self.consume(&TokenType::In, "expect 'in' after loop variable")?;
+    let iterable = self.expression()?;
+    self.consume(&TokenType::RightParen, "expect ')' after for-in clause")?;
+    let body = self.statement()?;
+
+    Ok(Stmt::ForIn {
+      name,
+      iterable,
+      body: Box::new(body),
+      label,
+    })
+  }
+
+  fn while_statement(&mut self, label: Option<Token>) -> Result<Stmt, ParseError> {
     self.consume(&TokenType::LeftParen, "expect '(' after 'while'")?;
     let condition = self.expression()?;
     self.consume(&TokenType::RightParen, "expect ')' after condition")?;
@@ -134,6 +264,8 @@ impl Parser {
     Ok(Stmt::While {
       condition: condition,
       body: Box::new(body),
+      increment: None,
+      label,
     })
   }
 
@@ -172,12 +304,45 @@ impl Parser {
     Ok(Stmt::Return { keyword, value })
   }
 
-  fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+  /// importDecl     → "import" STRING "as" IDENTIFIER ";"
+  ///                | "from" STRING "import" IDENTIFIER ( "," IDENTIFIER )* ";" ;
+  fn import_declaration(&mut self) -> Result<Stmt, ParseError> {
+    let keyword = self.previous();
+    let path = self.consume(&TokenType::LoxString, "expect module path")?;
+
+    let mut alias = None;
+    let mut names = vec![];
+    if keyword.token_type == TokenType::Import {
+      self.consume(&TokenType::As, "expect 'as' after module path")?;
+      alias = Some(self.consume(&TokenType::Identifier, "expect module name after 'as'")?);
+    } else {
+      self.consume(&TokenType::Import, "expect 'import' after module path")?;
+      loop {
+        names.push(self.consume(&TokenType::Identifier, "expect name to import")?);
+        if !self.amatch(&[TokenType::Comma]) {
+          break;
+        }
+      }
+    }
+
+    self.consume(&TokenType::Semicolon, "expect ';' after import")?;
+    Ok(Stmt::Import { path, alias, names })
+  }
+
+  /// varDecl        → ( "var" | "const" ) IDENTIFIER ( "=" expression )? ";" ;
+  fn var_declaration(&mut self, doc: Option<String>) -> Result<Stmt, ParseError> {
+    let constant = self.previous().token_type == TokenType::Const;
     let name = self.consume(&TokenType::Identifier, "expect variable name")?;
 
     let mut initializer = None;
     if self.amatch(&[TokenType::Equal]) {
       initializer = Some(self.expression()?);
+    } else if constant {
+      return Err(ParseError::InvalidExpression(
+        name.line,
+        name.lexeme,
+        "a constant must be initialized".to_string(),
+      ));
     }
 
     self.consume(
@@ -187,6 +352,8 @@ impl Parser {
     Ok(Stmt::Var {
       name: name,
       initializer: initializer,
+      constant,
+      doc,
     })
   }
 
@@ -197,25 +364,108 @@ impl Parser {
   }
 
   // TODO: add anonymous function syntax (lambdas)
-  fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
+  fn function(&mut self, kind: &str, doc: Option<String>) -> Result<Stmt, ParseError> {
     let name = self.consume(&TokenType::Identifier, &format!("expected {} name.", kind))?;
     self.consume(
       &TokenType::LeftParen,
       &format!("expected '(' after {} name", kind),
     )?;
-    let mut params: Vec<Token> = vec![];
+    let params = self.parameters(&TokenType::RightParen)?;
+    self.consume(&TokenType::RightParen, "expected ')' after parameters")?;
 
-    // parse args
-    if !self.check(&TokenType::RightParen) {
+    // parse body
+    self.consume(
+      &TokenType::LeftBrace,
+      &format!("expected '{{' before {} body", kind),
+    )?;
+    let body = self.block()?;
+    Ok(Stmt::Function {
+      name,
+      params: params.into(),
+      body: body.into(),
+      doc,
+    })
+  }
+
+  /// lambda         → "func" "(" parameters? ")" block | "|" parameters? "|" expression ;
+  fn lambda(&mut self) -> Result<Expr, ParseError> {
+    let keyword = self.previous();
+
+    let (params, body) = if keyword.token_type == TokenType::Func {
+      self.consume(&TokenType::LeftParen, "expected '(' after 'func'")?;
+      let params = self.parameters(&TokenType::RightParen)?;
+      self.consume(&TokenType::RightParen, "expected ')' after parameters")?;
+      self.consume(&TokenType::LeftBrace, "expected '{' before lambda body")?;
+      (params, self.block()?)
+    } else {
+      // the arrow shorthand's body is a single expression that gets returned
+      let params = self.parameters(&TokenType::Pipe)?;
+      self.consume(&TokenType::Pipe, "expected '|' after parameters")?;
+      let value = self.expression()?;
+      let body = vec![Stmt::Return {
+        keyword: keyword.clone(),
+        value: Some(value),
+      }];
+      (params, body)
+    };
+
+    Ok(Expr::Lambda {
+      keyword,
+      params: params.into(),
+      body: body.into(),
+    })
+  }
+
+  /// parameters     → param ( "," param )* ;
+  /// param          → "..." IDENTIFIER | IDENTIFIER ( "=" expression )? ;
+  fn parameters(&mut self, closing: &TokenType) -> Result<Vec<Param>, ParseError> {
+    let mut params: Vec<Param> = vec![];
+
+    if !self.check(closing) {
       loop {
-        if params.len() >= 255 {
-          return Err(ParseError::MaxNumFuncParameters(
-            params.last().unwrap().line,
-            params.last().unwrap().lexeme.clone(),
-            "can't have more than 255 function parameters".to_string(),
+        if let Some(last) = params.last() {
+          if params.len() >= 255 {
+            return Err(ParseError::MaxNumFuncParameters(
+              last.name.line,
+              last.name.lexeme.clone(),
+              "can't have more than 255 function parameters".to_string(),
+            ));
+          }
+          if last.rest {
+            return Err(ParseError::InvalidExpression(
+              last.name.line,
+              last.name.lexeme.clone(),
+              "a rest parameter must come last".to_string(),
+            ));
+          }
+        }
+
+        let rest = self.amatch(&[TokenType::Ellipsis]);
+        let name = self.consume(&TokenType::Identifier, "expected paramter name")?;
+        let default = if !rest && self.amatch(&[TokenType::Equal]) {
+          // a bare '|' would close the parameters of a shorthand lambda, so its defaults can't
+          // contain a bitwise or
+          Some(if *closing == TokenType::Pipe {
+            self.bit_xor()?
+          } else {
+            self.expression()?
+          })
+        } else {
+          None
+        };
+
+        if default.is_none() && !rest && params.last().is_some_and(|p| p.default.is_some()) {
+          return Err(ParseError::InvalidExpression(
+            name.line,
+            name.lexeme,
+            "a parameter without a default can't follow one with a default".to_string(),
           ));
         }
-        params.push(self.consume(&TokenType::Identifier, "expected paramter name")?);
+        params.push(Param {
+          name,
+          default,
+          rest,
+        });
 
         if !self.amatch(&[TokenType::Comma]) {
           break;
@@ -223,15 +473,7 @@ impl Parser {
       }
     }
 
-    self.consume(&TokenType::RightParen, "expected ')' after parameters")?;
-
-    // parse body
-    self.consume(
-      &TokenType::LeftBrace,
-      &format!("expected '{{' before {} body", kind),
-    )?;
-    let body = self.block()?;
-    Ok(Stmt::Function { name, params, body })
+    Ok(params)
   }
 
   fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
@@ -246,7 +488,18 @@ impl Parser {
   }
 
   fn assignment(&mut self) -> Result<Expr, ParseError> {
-    let expr = self.or()?;
+    let expr = self.conditional()?;
+
+    if self.amatch(&[
+      TokenType::PlusEqual,
+      TokenType::MinusEqual,
+      TokenType::StarEqual,
+      TokenType::SlashEqual,
+    ]) {
+      let operator = self.previous();
+      let value = self.assignment()?;
+      return Self::compound(expr, operator, value, false);
+    }
 
     if !self.amatch(&[TokenType::Equal]) {
       return Ok(expr);
@@ -260,6 +513,16 @@ impl Parser {
         name,
         value: Box::new(value),
       }),
+      Expr::Index {
+        object,
+        bracket,
+        index,
+      } => Ok(Expr::IndexSet {
+        object,
+        bracket,
+        index,
+        value: Box::new(value),
+      }),
       _ => Err(ParseError::InvalidAssignment(
         equals.line,
         equals.lexeme,
@@ -268,6 +531,58 @@ impl Parser {
     }
   }
 
+  /// `target op= value`, `++target` and `target++` all read, update and write back `target`
+  fn compound(
+    target: Expr,
+    operator: Token,
+    value: Expr,
+    postfix: bool,
+  ) -> Result<Expr, ParseError> {
+    if !matches!(target, Expr::Variable { .. } | Expr::Index { .. }) {
+      return Err(ParseError::InvalidAssignment(
+        operator.line,
+        operator.lexeme,
+        "invalid assignment target".to_string(),
+      ));
+    }
+
+    let token_type = match operator.token_type {
+      TokenType::PlusEqual | TokenType::PlusPlus => TokenType::Plus,
+      TokenType::MinusEqual | TokenType::MinusMinus => TokenType::Minus,
+      TokenType::StarEqual => TokenType::Star,
+      _ => TokenType::Slash,
+    };
+
+    Ok(Expr::Compound {
+      target: Box::new(target),
+      operator: Token {
+        token_type,
+        ..operator
+      },
+      value: Box::new(value),
+      postfix,
+    })
+  }
+
+  /// conditional    → logic_or ( "?" expression ":" conditional )? ;
+  fn conditional(&mut self) -> Result<Expr, ParseError> {
+    let condition = self.or()?;
+
+    if !self.amatch(&[TokenType::Question]) {
+      return Ok(condition);
+    }
+
+    let then_branch = self.expression()?;
+    self.consume(&TokenType::Colon, "expect ':' after then branch of '?'")?;
+    let else_branch = self.conditional()?;
+
+    Ok(Expr::Conditional {
+      condition: Box::new(condition),
+      then_branch: Box::new(then_branch),
+      else_branch: Box::new(else_branch),
+    })
+  }
+
   fn or(&mut self) -> Result<Expr, ParseError> {
     let mut expr = self.and()?;
 
@@ -336,9 +651,59 @@ impl Parser {
     }
   }
 
+  /// like check, but `distance` tokens past the current one
+  fn check_ahead(&mut self, distance: usize, token_type: &TokenType) -> bool {
+    self.fill(distance + 1);
+    match self.lookahead.get(distance) {
+      Some((token, _)) => token.token_type == *token_type,
+      None => false,
+    }
+  }
+
+  /// buffers up to `count` tokens, fewer when the input ends first. doc comments are taken out of
+  /// the stream here, consecutive lines join into one doc for the token after them
+  fn fill(&mut self, count: usize) {
+    while self.lookahead.len() < count {
+      if self
+        .lookahead
+        .back()
+        .is_some_and(|(token, _)| token.is_eof())
+      {
+        return;
+      }
+
+      let token = match self.tokens.next() {
+        Some(Ok(token)) if token.token_type == TokenType::DocComment => {
+          let line = token.literal.to_str().unwrap_or_default();
+          self.doc = Some(match self.doc.take() {
+            Some(doc) => doc + "\n" + &line,
+            None => line,
+          });
+          continue;
+        }
+        Some(Ok(token)) => token,
+        end => {
+          if let Some(Err(e)) = end {
+            self.lex_error = Some(e);
+          }
+          let (line, column) = self
+            .lookahead
+            .back()
+            .map(|(token, _)| token)
+            .or(self.previous.as_ref())
+            .map_or((0, 0), |token| (token.line, token.column));
+          Token::new(TokenType::Eof, "", Object::None, line, column)
+        }
+      };
+      let doc = self.doc.take();
+      self.lookahead.push_back((token, doc));
+    }
+  }
+
   fn advance(&mut self) -> Token {
     if !self.peek().is_eof() {
-      self.current += 1;
+      self.previous = self.lookahead.pop_front().map(|(token, _)| token);
+      self.fill(1);
     }
 
     self.previous()
@@ -346,17 +711,17 @@ impl Parser {
 
   fn peek(&self) -> Token {
     // TODO: get rid of unwrap and clone
-    self.tokens.get(self.current).unwrap().clone()
+    self.lookahead.front().unwrap().0.clone()
   }
 
   fn previous(&self) -> Token {
     // TODO: get rid of unwrap and clone
-    self.tokens.get(self.current - 1).unwrap().clone()
+    self.previous.clone().unwrap()
   }
 
-  /// comparison     → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
+  /// comparison     → bit_or ( ( ">" | ">=" | "<" | "<=" ) bit_or )* ;
   fn comparison(&mut self) -> Result<Expr, ParseError> {
-    let mut expr = self.term()?;
+    let mut expr = self.bit_or()?;
 
     while self.amatch(&[
       TokenType::Greater,
@@ -365,7 +730,51 @@ impl Parser {
       TokenType::LessEqual,
     ]) {
       let operator = self.previous();
-      let right = self.term()?;
+      let right = self.bit_or()?;
+      expr = Expr::Binary {
+        left: Box::new(expr),
+        operator,
+        right: Box::new(right),
+      };
+    }
+
+    Ok(expr)
+  }
+
+  /// bit_or         → bit_xor ( "|" bit_xor )* ;
+  fn bit_or(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenType::Pipe], Self::bit_xor)
+  }
+
+  /// bit_xor        → bit_and ( "^" bit_and )* ;
+  fn bit_xor(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenType::Caret], Self::bit_and)
+  }
+
+  /// bit_and        → shift ( "&" shift )* ;
+  fn bit_and(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(&[TokenType::Ampersand], Self::shift)
+  }
+
+  /// shift          → term ( ( "<<" | ">>" ) term )* ;
+  fn shift(&mut self) -> Result<Expr, ParseError> {
+    self.left_associative(
+      &[TokenType::LessLess, TokenType::GreaterGreater],
+      Self::term,
+    )
+  }
+
+  /// a chain of binary `operators` between operands parsed by `operand`, grouped from the left
+  fn left_associative(
+    &mut self,
+    operators: &[TokenType],
+    operand: fn(&mut Self) -> Result<Expr, ParseError>,
+  ) -> Result<Expr, ParseError> {
+    let mut expr = operand(self)?;
+
+    while self.amatch(operators) {
+      let operator = self.previous();
+      let right = operand(self)?;
       expr = Expr::Binary {
         left: Box::new(expr),
         operator,
@@ -393,11 +802,16 @@ impl Parser {
     Ok(expr)
   }
 
-  /// factor         → unary ( ( "/" | "*" ) unary )* ;
+  /// factor         → unary ( ( "/" | "*" | "%" | "~/" ) unary )* ;
   fn factor(&mut self) -> Result<Expr, ParseError> {
     let mut expr = self.unary()?;
 
-    while self.amatch(&[TokenType::Slash, TokenType::Star]) {
+    while self.amatch(&[
+      TokenType::Slash,
+      TokenType::Star,
+      TokenType::Percent,
+      TokenType::TildeSlash,
+    ]) {
       let operator = self.previous();
       let right = self.unary()?;
       expr = Expr::Binary {
@@ -410,9 +824,13 @@ impl Parser {
     Ok(expr)
   }
 
-  /// unary          → ( "!" | "-" ) unary
+  /// unary          → ( "!" | "-" | "~" ) unary
   fn unary(&mut self) -> Result<Expr, ParseError> {
-    if self.amatch(&[TokenType::Bang, TokenType::Minus]) {
+    if self.amatch(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+      let operator = self.previous();
+      let target = self.unary()?;
+      Self::compound(target, operator, Self::one(), false)
+    } else if self.amatch(&[TokenType::Bang, TokenType::Minus, TokenType::Tilde]) {
       let operator = self.previous();
       let right = self.unary()?;
       Ok(Expr::Unary {
@@ -420,7 +838,41 @@ impl Parser {
         right: Box::new(right),
       })
     } else {
-      self.call()
+      self.power()
+    }
+  }
+
+  /// power          → postfix ( "**" unary )? ;
+  /// right associative and binding tighter than a unary minus on its left, so -2 ** 2 is -4
+  fn power(&mut self) -> Result<Expr, ParseError> {
+    let expr = self.postfix()?;
+
+    if self.amatch(&[TokenType::StarStar]) {
+      let operator = self.previous();
+      let right = self.unary()?;
+      return Ok(Expr::Binary {
+        left: Box::new(expr),
+        operator,
+        right: Box::new(right),
+      });
+    }
+
+    Ok(expr)
+  }
+
+  /// postfix        → call ( "++" | "--" )? ;
+  fn postfix(&mut self) -> Result<Expr, ParseError> {
+    let expr = self.call()?;
+    if self.amatch(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+      return Self::compound(expr, self.previous(), Self::one(), true);
+    }
+    Ok(expr)
+  }
+
+  /// what `++` and `--` add or subtract
+  fn one() -> Expr {
+    Expr::Literal {
+      value: Object::Int(1),
     }
   }
 
@@ -430,6 +882,21 @@ impl Parser {
     loop {
       if self.amatch(&[TokenType::LeftParen]) {
         expr = self.finish_call(&expr)?;
+      } else if self.amatch(&[TokenType::LeftBracket]) {
+        let bracket = self.previous();
+        let index = self.expression()?;
+        self.consume(&TokenType::RightBracket, "expect ']' after index")?;
+        expr = Expr::Index {
+          object: Box::new(expr),
+          bracket,
+          index: Box::new(index),
+        };
+      } else if self.amatch(&[TokenType::Dot]) {
+        let name = self.consume(&TokenType::Identifier, "expect name after '.'")?;
+        expr = Expr::Get {
+          object: Box::new(expr),
+          name,
+        };
       } else {
         break;
       }
@@ -438,27 +905,43 @@ impl Parser {
     Ok(expr)
   }
 
+  /// arguments      → expression ( "," expression )* ( "," IDENTIFIER ":" expression )* ;
   fn finish_call(&mut self, callee: &Expr) -> Result<Expr, ParseError> {
     let mut arguments = vec![];
+    let mut named: Vec<(Token, Expr)> = vec![];
     if !self.check(&TokenType::RightParen) {
       loop {
-        if arguments.len() >= 255 {
+        if arguments.len() + named.len() >= 255 {
+          // recorded but not returned, the parser is still in a valid state
           let curr = self.peek();
-          // TODO: don't throw error here! needs to just be a call to error
-          LoxError::report(&LoxError::ParseError(ParseError::MaxNumFuncParameters(
+          self.errors.push(ParseError::MaxNumFuncParameters(
             curr.line,
             curr.lexeme,
             "can't have more than 255 arguments".to_string(),
-          )));
-          /*
-          return Err(ParseError::MaxNumFuncParameters(
-              curr.line,
-              curr.lexeme,
-              "can't have more than 255 arguments".to_string(),
           ));
-          */
         }
-        arguments.push(self.expression()?);
+
+        if self.check(&TokenType::Identifier) && self.check_ahead(1, &TokenType::Colon) {
+          let name = self.advance();
+          self.advance(); // the ':'
+          if named.iter().any(|(other, _)| other.lexeme == name.lexeme) {
+            self.errors.push(ParseError::InvalidExpression(
+              name.line,
+              name.lexeme.clone(),
+              "argument given by name more than once".to_string(),
+            ));
+          }
+          named.push((name, self.expression()?));
+        } else if let Some((name, _)) = named.last() {
+          return Err(ParseError::InvalidExpression(
+            name.line,
+            name.lexeme.clone(),
+            "positional arguments can't follow named ones".to_string(),
+          ));
+        } else {
+          arguments.push(self.expression()?);
+        }
+
         if !self.amatch(&[TokenType::Comma]) {
           break;
         }
@@ -471,10 +954,12 @@ impl Parser {
       callee: Box::new(callee.clone()),
       paren,
       arguments,
+      named,
     })
   }
 
-  /// primary        → NUMBER | STRING | "true" | "false" | "none" | "(" expression ")" ;
+  /// primary        → NUMBER | STRING | "true" | "false" | "none" | "(" expression ")" | list
+  ///                  | map ;
   fn primary(&mut self) -> Result<Expr, ParseError> {
     if self.amatch(&[TokenType::False]) {
       Ok(Expr::Literal {
@@ -502,21 +987,86 @@ impl Parser {
       Ok(Expr::Grouping {
         expression: Box::new(expr),
       })
+    } else if self.amatch(&[TokenType::Interpolation]) {
+      self.interpolation()
+    } else if self.amatch(&[TokenType::Func, TokenType::Pipe]) {
+      self.lambda()
+    } else if self.amatch(&[TokenType::LeftBracket]) {
+      self.list()
+    } else if self.amatch(&[TokenType::LeftBrace]) {
+      // only reachable in expression position, a '{' starting a statement is always a block
+      self.map()
     } else {
-      LoxError::report(&LoxError::ParseError(ParseError::InvalidExpression(
+      Err(ParseError::InvalidExpression(
         self.peek().line,
         self.peek().lexeme,
         "expect expression".to_string(),
-      )));
-      self.synchronize();
+      ))
+    }
+  }
 
-      // TODO: possibly remove this all here as its handled in the parse func now
+  /// interpolation  → ( INTERPOLATION expression )+ STRING ;
+  fn interpolation(&mut self) -> Result<Expr, ParseError> {
+    let start = self.previous();
+    let mut parts = vec![];
+    let mut text = start.clone();
 
-      // or err here
-      Ok(Expr::Literal {
-        value: Object::None,
-      })
+    loop {
+      if text.literal != Object::String(String::new()) {
+        parts.push(Expr::Literal {
+          value: text.literal,
+        });
+      }
+      parts.push(self.expression()?);
+
+      if !self.amatch(&[TokenType::Interpolation]) {
+        break;
+      }
+      text = self.previous();
+    }
+
+    let end = self.consume(
+      &TokenType::LoxString,
+      "expect '}' after interpolated expression",
+    )?;
+    if end.literal != Object::String(String::new()) {
+      parts.push(Expr::Literal { value: end.literal });
+    }
+
+    Ok(Expr::Interpolation { start, parts })
+  }
+
+  /// list           → "[" ( expression ( "," expression )* ","? )? "]" ;
+  fn list(&mut self) -> Result<Expr, ParseError> {
+    let bracket = self.previous();
+    let mut elements = vec![];
+    while !self.check(&TokenType::RightBracket) {
+      elements.push(self.expression()?);
+      if !self.amatch(&[TokenType::Comma]) {
+        break;
+      }
+    }
+
+    self.consume(&TokenType::RightBracket, "expect ']' after list elements")?;
+    Ok(Expr::List { bracket, elements })
+  }
+
+  /// map            → "{" ( entry ( "," entry )* ","? )? "}" ;
+  /// entry          → expression ":" expression ;
+  fn map(&mut self) -> Result<Expr, ParseError> {
+    let brace = self.previous();
+    let mut entries = vec![];
+    while !self.check(&TokenType::RightBrace) {
+      let key = self.expression()?;
+      self.consume(&TokenType::Colon, "expect ':' after map key")?;
+      entries.push((key, self.expression()?));
+      if !self.amatch(&[TokenType::Comma]) {
+        break;
+      }
     }
+
+    self.consume(&TokenType::RightBrace, "expect '}' after map entries")?;
+    Ok(Expr::Map { brace, entries })
   }
 
   fn consume(&mut self, token_type: &TokenType, msg: &str) -> Result<Token, ParseError> {
@@ -557,23 +1107,23 @@ impl Parser {
 #[cfg(test)]
 mod parser_tests {
   use super::*;
-  use crate::lexer::Lexer;
+  use crate::{errors::LoxError, lexer::Lexer};
+  use std::io;
 
   fn run(source: &str) -> Result<Vec<Stmt>, LoxError> {
-    let mut lexer = Lexer::new(source);
-    let tokens = match lexer.lex_tokens() {
-      Ok(tokens) => tokens,
-      Err(e) => {
-        LoxError::report(&LoxError::LexError(e.clone()));
-        return Err(LoxError::LexError(e));
-      }
-    };
+    let mut parser = Parser::new(Lexer::new(source));
+    let parsed = parser.parse();
+    if let Some(e) = parser.lex_error() {
+      LoxError::report(&LoxError::LexError(e.clone()), &mut io::stdout());
+      return Err(LoxError::LexError(e.clone()));
+    }
 
-    let mut parser = Parser::new(tokens);
-    let statements = match parser.parse() {
+    let statements = match parsed {
       Ok(statements) => statements,
       Err(e) => {
-        LoxError::report(&LoxError::ParseError(e.clone()));
+        for err in parser.errors() {
+          LoxError::report(&LoxError::ParseError(err.clone()), &mut io::stdout());
+        }
         return Err(LoxError::ParseError(e));
       }
     };
@@ -592,4 +1142,58 @@ mod parser_tests {
     let r = run(c);
     assert!(r.is_err());
   }
+
+  #[test]
+  fn constants_must_be_initialized() {
+    assert!(run("const limit;").is_err());
+    match run("const limit = 3;").unwrap().as_slice() {
+      [Stmt::Var { constant, .. }] => assert!(*constant),
+      _ => panic!("expected a single var statement"),
+    }
+  }
+
+  #[test]
+  fn parameter_and_argument_order() {
+    assert!(run("func f(a, b = 1, ...rest) {}").is_ok());
+    assert!(run("var f = |a, b = 1 ^ 2| a + b;").is_ok());
+    assert!(run("f(1, b: 2, c: 3);").is_ok());
+
+    for source in [
+      "func f(a = 1, b) {}",
+      "func f(...rest, a) {}",
+      "f(b: 2, 1);",
+      "f(b: 2, b: 3);",
+    ] {
+      assert!(run(source).is_err(), "{} should not parse", source);
+    }
+  }
+
+  #[test]
+  fn doc_comments_attach_to_declarations() {
+    let c = r#"
+    /// adds two numbers
+    ///   and returns the sum
+    func add(a, b) { return a + b; }
+    //// not a doc comment
+    var plain = 1;
+    /// the answer
+    var answer = add(40, 2);
+    "#;
+    let r = run(c).unwrap();
+    let docs: Vec<Option<String>> = r
+      .iter()
+      .map(|stmt| match stmt {
+        Stmt::Function { doc, .. } | Stmt::Var { doc, .. } => doc.clone(),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(
+      docs,
+      vec![
+        Some("adds two numbers\n  and returns the sum".to_string()),
+        None,
+        Some("the answer".to_string()),
+      ]
+    );
+  }
 }
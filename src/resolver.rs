@@ -1,10 +1,10 @@
 use crate::{
   errors::LoxError,
   interpreter::Interpreter,
-  types::{Expr, ExprVisitor, Object, Stmt, StmtVisitor, Token},
+  types::{Expr, ExprVisitor, Object, Param, Stmt, StmtVisitor, Token},
 };
 
-use std::collections::HashMap;
+use std::{collections::HashMap, rc::Rc};
 
 #[derive(Clone)]
 enum FunctionType {
@@ -12,10 +12,19 @@ enum FunctionType {
   Function,
 }
 
+/// what's known about a local variable while the scope it's declared in is resolved
+#[derive(Clone, Copy)]
+struct Binding {
+  defined: bool, // false until its initializer has been resolved
+  constant: bool,
+}
+
 pub struct Resolver<'a> {
   interpreter: &'a mut Interpreter,
-  scopes: Vec<HashMap<String, bool>>, // this is a stack so only push and pop
+  scopes: Vec<HashMap<String, Binding>>, // this is a stack so only push and pop
   current_function: FunctionType,
+  loops: Vec<Option<String>>, // labels of the loops enclosing the current statement
+  errors: Vec<LoxError>,
 }
 
 impl<'a> Resolver<'a> {
@@ -24,9 +33,24 @@ impl<'a> Resolver<'a> {
       interpreter,
       scopes: vec![],
       current_function: FunctionType::None,
+      loops: vec![],
+      errors: vec![],
     }
   }
 
+  /// consumes the resolver, handing back every semantic error it found
+  pub fn into_errors(self) -> Vec<LoxError> {
+    self.errors
+  }
+
+  fn error(&mut self, name: &Token, msg: &str) {
+    self.errors.push(LoxError::SemanticPassError(
+      name.line,
+      name.lexeme.to_string(),
+      msg.to_string(),
+    ));
+  }
+
   pub fn resolve_stmts(&mut self, statements: &[Stmt]) {
     statements.iter().for_each(|s| self.resolve_stmt(s));
   }
@@ -40,7 +64,7 @@ impl<'a> Resolver<'a> {
   }
 
   fn resolve_local(&mut self, name: &Token) {
-    for i in self.scopes.len() - 1..0 {
+    for i in (0..self.scopes.len()).rev() {
       if self.scopes[i].contains_key(&name.lexeme) {
         self.interpreter.resolve(
           &Expr::Variable { name: name.clone() },
@@ -51,21 +75,59 @@ impl<'a> Resolver<'a> {
     }
   }
 
-  fn resolve_function(&mut self, params: &Vec<Token>, body: &Vec<Stmt>, func_type: FunctionType) {
+  /// locals declared with const can't be assigned, globals are only checked when the assignment runs
+  fn check_assignable(&mut self, name: &Token) {
+    let binding = self
+      .scopes
+      .iter()
+      .rev()
+      .find_map(|scope| scope.get(&name.lexeme));
+    if binding.is_some_and(|b| b.constant) {
+      self.error(name, "Can't assign to a constant.");
+    }
+  }
+
+  fn resolve_function(&mut self, params: &[Param], body: &[Stmt], func_type: FunctionType) {
     let enclosing_function = self.current_function.clone();
     self.current_function = func_type;
+    // a break in a function body can't reach the loops around the function
+    let enclosing_loops = std::mem::take(&mut self.loops);
 
     self.begin_scope();
 
+    // a default is evaluated in the call's scope, seeing only the parameters before it
     params.iter().for_each(|p| {
-      self.declare(p);
-      self.define(p);
+      if let Some(default) = &p.default {
+        self.resolve_expr(default);
+      }
+      self.declare(&p.name);
+      self.define(&p.name);
     });
 
     self.resolve_stmts(body);
     self.end_scope();
 
     self.current_function = enclosing_function.clone();
+    self.loops = enclosing_loops;
+  }
+
+  fn resolve_loop_body(&mut self, body: &Stmt, label: &Option<Token>) {
+    self.loops.push(label.as_ref().map(|l| l.lexeme.clone()));
+    self.resolve_stmt(body);
+    self.loops.pop();
+  }
+
+  fn resolve_jump(&mut self, keyword: &Token, label: &Option<Token>) {
+    match label {
+      _ if self.loops.is_empty() => {
+        let msg = format!("Can't use '{}' outside of a loop.", keyword.lexeme);
+        self.error(keyword, &msg);
+      }
+      Some(label) if !self.loops.contains(&Some(label.lexeme.clone())) => {
+        self.error(label, "No enclosing loop with this label.");
+      }
+      _ => {}
+    }
   }
 
   fn begin_scope(&mut self) {
@@ -77,30 +139,67 @@ impl<'a> Resolver<'a> {
   }
 
   fn declare(&mut self, name: &Token) {
-    if let Some(mut scope) = self.scopes.peek_mut() {
-      if scope.contains_key(&name.lexeme) {
-        LoxError::report(&LoxError::Error);
-      }
-      scope.try_insert(name.lexeme.clone(), false).unwrap();
+    let Some(mut scope) = self.scopes.peek_mut() else {
+      return;
+    };
+
+    let binding = Binding {
+      defined: false,
+      constant: false,
+    };
+    if scope.try_insert(name.lexeme.clone(), binding).is_err() {
+      self.error(name, "Already a variable with this name in this scope.");
     }
   }
 
   fn define(&mut self, name: &Token) {
+    self.define_as(name, false);
+  }
+
+  fn define_as(&mut self, name: &Token, constant: bool) {
     if let Some(mut scope) = self.scopes.peek_mut() {
-      if scope.contains_key(&name.lexeme) {
-        LoxError::report(&LoxError::SemanticPassError(
-          name.line,
-          name.lexeme.to_string(),
-          "Already a variable with this name in this scope.".to_string(),
-        ));
-      }
-      scope.try_insert(name.lexeme.clone(), false).unwrap();
-      scope.try_insert(name.lexeme.clone(), true).unwrap();
+      let binding = Binding {
+        defined: true,
+        constant,
+      };
+      scope.insert(name.lexeme.clone(), binding);
     }
   }
 }
 
 impl<'a> ExprVisitor<()> for Resolver<'a> {
+  fn visit_compound_expr(
+    &mut self,
+    target: &Expr,
+    _operator: &Token,
+    value: &Expr,
+    _postfix: bool,
+  ) {
+    if let Expr::Variable { name } = target {
+      self.check_assignable(name);
+    }
+    self.resolve_expr(target);
+    self.resolve_expr(value);
+  }
+
+  fn visit_interpolation_expr(&mut self, _start: &Token, parts: &[Expr]) {
+    parts.iter().for_each(|p| self.resolve_expr(p));
+  }
+
+  fn visit_conditional_expr(&mut self, condition: &Expr, then_branch: &Expr, else_branch: &Expr) {
+    self.resolve_expr(condition);
+    self.resolve_expr(then_branch);
+    self.resolve_expr(else_branch);
+  }
+
+  fn visit_get_expr(&mut self, object: &Expr, _name: &Token) {
+    self.resolve_expr(object);
+  }
+
+  fn visit_lambda_expr(&mut self, _keyword: &Token, params: &Rc<[Param]>, body: &Rc<[Stmt]>) {
+    self.resolve_function(params, body, FunctionType::Function);
+  }
+
   fn visit_binary_expr(&mut self, left: &Expr, _operator: &Token, right: &Expr) {
     self.resolve_expr(left);
     self.resolve_expr(right);
@@ -120,15 +219,16 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
 
   fn visit_var_expr(&mut self, name: &Token) {
     if let Some(last) = self.scopes.last()
-      && !last.get(&name.lexeme).unwrap()
+      && last.get(&name.lexeme).is_some_and(|b| !b.defined)
     {
-      LoxError::report(&LoxError::Error); // TODO: compile time error
+      self.error(name, "Can't read local variable in its own initializer.");
     }
 
     self.resolve_local(name);
   }
 
   fn visit_assign_expr(&mut self, name: &Token, value: &Expr) {
+    self.check_assignable(name);
     self.resolve_expr(value);
     self.resolve_local(name);
   }
@@ -138,9 +238,32 @@ impl<'a> ExprVisitor<()> for Resolver<'a> {
     self.resolve_expr(right);
   }
 
-  fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr]) {
+  fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr], named: &[(Token, Expr)]) {
     self.resolve_expr(callee);
     arguments.iter().for_each(|arg| self.resolve_expr(arg));
+    named.iter().for_each(|(_, arg)| self.resolve_expr(arg));
+  }
+
+  fn visit_list_expr(&mut self, _bracket: &Token, elements: &[Expr]) {
+    elements.iter().for_each(|e| self.resolve_expr(e));
+  }
+
+  fn visit_map_expr(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) {
+    entries.iter().for_each(|(key, value)| {
+      self.resolve_expr(key);
+      self.resolve_expr(value);
+    });
+  }
+
+  fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) {
+    self.resolve_expr(object);
+    self.resolve_expr(index);
+  }
+
+  fn visit_index_set_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr, value: &Expr) {
+    self.resolve_expr(value);
+    self.resolve_expr(object);
+    self.resolve_expr(index);
   }
 }
 
@@ -153,12 +276,12 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
     self.resolve_expr(expression);
   }
 
-  fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) {
+  fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>, constant: bool) {
     self.declare(name);
     if let Some(i) = initializer {
       self.resolve_expr(i);
     }
-    self.define(name);
+    self.define_as(name, constant);
   }
 
   fn visit_block_stmt(&mut self, statements: &[Stmt]) {
@@ -175,12 +298,82 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
     }
   }
 
-  fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) {
+  fn visit_while_stmt(
+    &mut self,
+    condition: &Expr,
+    body: &Stmt,
+    increment: &Option<Expr>,
+    label: &Option<Token>,
+  ) {
     self.resolve_expr(condition);
-    self.resolve_stmt(body);
+    self.resolve_loop_body(body, label);
+    if let Some(inc) = increment {
+      self.resolve_expr(inc);
+    }
   }
 
-  fn visit_function_stmt(&mut self, name: &Token, params: &Vec<Token>, body: &Vec<Stmt>) {
+  fn visit_for_in_stmt(
+    &mut self,
+    name: &Token,
+    iterable: &Expr,
+    body: &Stmt,
+    label: &Option<Token>,
+  ) {
+    self.resolve_expr(iterable);
+
+    // the loop variable gets a fresh scope each iteration, wrapping the body
+    self.begin_scope();
+    self.declare(name);
+    self.define(name);
+    self.resolve_loop_body(body, label);
+    self.end_scope();
+  }
+
+  fn visit_break_stmt(&mut self, keyword: &Token, label: &Option<Token>) {
+    self.resolve_jump(keyword, label);
+  }
+
+  fn visit_continue_stmt(&mut self, keyword: &Token, label: &Option<Token>) {
+    self.resolve_jump(keyword, label);
+  }
+
+  fn visit_import_stmt(&mut self, _path: &Token, alias: &Option<Token>, names: &[Token]) {
+    for name in alias.iter().chain(names) {
+      self.declare(name);
+      self.define(name);
+    }
+  }
+
+  fn visit_throw_stmt(&mut self, _keyword: &Token, value: &Expr) {
+    self.resolve_expr(value);
+  }
+
+  fn visit_try_stmt(
+    &mut self,
+    body: &[Stmt],
+    catch: &Option<(Token, Vec<Stmt>)>,
+    finally: &Option<Vec<Stmt>>,
+  ) {
+    self.begin_scope();
+    self.resolve_stmts(body);
+    self.end_scope();
+
+    if let Some((name, handler)) = catch {
+      self.begin_scope();
+      self.declare(name);
+      self.define(name);
+      self.resolve_stmts(handler);
+      self.end_scope();
+    }
+
+    if let Some(finally) = finally {
+      self.begin_scope();
+      self.resolve_stmts(finally);
+      self.end_scope();
+    }
+  }
+
+  fn visit_function_stmt(&mut self, name: &Token, params: &Rc<[Param]>, body: &Rc<[Stmt]>) {
     self.declare(name);
     self.define(name);
 
@@ -188,13 +381,8 @@ impl<'a> StmtVisitor<()> for Resolver<'a> {
   }
 
   fn visit_return_stmt(&mut self, keyword: &Token, value: &Option<Expr>) {
-    match self.current_function {
-      FunctionType::None => LoxError::report(&LoxError::SemanticPassError(
-        keyword.line,
-        keyword.lexeme.to_string(),
-        "Can't return from top-level code.".to_string(),
-      )),
-      _ => {}
+    if let FunctionType::None = self.current_function {
+      self.error(keyword, "Can't return from top-level code.");
     }
 
     if let Some(v) = value {
@@ -1,21 +1,42 @@
-use std::{cmp, fmt, hash, rc::Rc};
+use std::{
+  cell::RefCell,
+  cmp,
+  collections::{HashMap, HashSet},
+  fmt, hash,
+  rc::Rc,
+};
 
-use crate::{callable::Callable, errors::RuntimeError};
+use crate::{
+  bignum::{BigInt, Decimal},
+  callable::Callable,
+  errors::RuntimeError,
+  modules::Module,
+};
 
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub enum TokenType {
   // single-character tokens
   LeftParen,
   RightParen,
+  Ampersand,
+  Caret,
   LeftBrace,
   RightBrace,
+  LeftBracket,
+  RightBracket,
+  Colon,
   Comma,
   Dot,
+  Ellipsis, // `...` before a rest parameter
   Minus,
+  Percent,
+  Pipe,
   Plus,
+  Question,
   Semicolon,
   Slash,
   Star,
+  Tilde,
 
   // one or two character tokens
   Bang,
@@ -24,29 +45,52 @@ pub enum TokenType {
   EqualEqual,
   Greater,
   GreaterEqual,
+  GreaterGreater,
   Less,
   LessEqual,
+  LessLess,
+  MinusEqual,
+  MinusMinus,
+  PlusEqual,
+  PlusPlus,
+  SlashEqual,
+  StarEqual,
+  StarStar,
+  TildeSlash, // `~/` floor division, `//` is always a comment
 
   // literals
   Identifier,
   LoxString,
+  DocComment, // never reaches the parser's token stream, it's attached to the next declaration
+  Interpolation, // string text followed by `${`, the embedded expression's tokens come next
   Number,
 
   // keywords
   And,
+  As,
+  Break,
+  Catch,
   Class,
+  Const,
+  Continue,
   Else,
   False,
+  Finally,
   Func,
   For,
+  From,
   If,
+  Import,
+  In,
   r#None,
   Or,
   Print,
   Return,
   Super,
   This,
+  Throw,
   True,
+  Try,
   Var,
   While,
 
@@ -56,12 +100,314 @@ pub enum TokenType {
 #[derive(Clone)]
 pub enum Object {
   r#String(String),
+  Int(i64),
   Number(f64),
+  BigInt(Rc<BigInt>),
+  Decimal(Rc<Decimal>),
   Bool(bool),
   Callable(Rc<dyn Callable>),
+  List(Rc<RefCell<Vec<Object>>>),
+  Map(Rc<RefCell<LoxMap>>),
+  Module(Rc<Module>),
   None,
 }
 
+/// prints floats with a fractional part even when it's zero, so they're never mistaken for ints.
+/// otherwise the digits are written out in full, never in exponent form
+fn float_to_string(n: f64) -> String {
+  let digits = n.to_string();
+  if n.is_finite() && !digits.contains('.') {
+    digits + ".0"
+  } else {
+    digits
+  }
+}
+
+/// the int a float is exactly equal to, if there is one
+fn exact_int(n: f64) -> Option<i64> {
+  // i64::MIN is a power of two so it converts exactly, i64::MAX rounds up to 2^63
+  (n.fract() == 0.0 && n >= i64::MIN as f64 && n < i64::MAX as f64).then_some(n as i64)
+}
+
+/// orders two numbers of any kind by their exact values, none if either isn't a number or is NaN
+fn compare_numbers(a: &Object, b: &Object) -> Option<cmp::Ordering> {
+  match (a, b) {
+    (Object::Int(a), Object::Int(b)) => Some(a.cmp(b)),
+    (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
+    // ints this small all convert to floats exactly
+    (Object::Int(a), Object::Number(b)) if a.unsigned_abs() <= 1 << 53 => {
+      (*a as f64).partial_cmp(b)
+    }
+    (Object::Number(a), Object::Int(b)) if b.unsigned_abs() <= 1 << 53 => {
+      a.partial_cmp(&(*b as f64))
+    }
+    _ => {
+      let exact = |n: &Object| match n {
+        Object::Number(n) => Decimal::from_f64(*n),
+        n => n.to_decimal(),
+      };
+      match (exact(a), exact(b)) {
+        (Some(a), Some(b)) => Some(a.cmp(&b)),
+        // NaN, an infinity or something that isn't a number
+        _ => a.to_num()?.partial_cmp(&b.to_num()?),
+      }
+    }
+  }
+}
+
+/// a list or map being written out, with how many of its elements have been written so far
+enum Writing {
+  List(Rc<RefCell<Vec<Object>>>, usize),
+  Map(Rc<RefCell<LoxMap>>, usize),
+}
+
+/// writes a list or map the way `repr` would. nested collections are walked with a stack of their
+/// own, so nesting however deep can't overflow the native one, and a collection that contains
+/// itself prints as [...] or {...} where it comes round again
+fn collection_to_string(collection: &Object) -> String {
+  // writes what can be written straight away, hands back a collection to open
+  fn element(value: &Object, out: &mut String) -> Option<Object> {
+    match value {
+      Object::List(_) | Object::Map(_) => Some(value.clone()),
+      _ => {
+        out.push_str(&value.repr());
+        None
+      }
+    }
+  }
+
+  let mut out = String::new();
+  let mut stack = vec![];
+  let mut open = HashSet::new(); // collections on the stack
+  let mut next = Some(collection.clone());
+  loop {
+    match next.take() {
+      Some(Object::List(items)) if open.insert(Rc::as_ptr(&items) as *const ()) => {
+        out.push('[');
+        stack.push(Writing::List(items, 0));
+      }
+      Some(Object::Map(map)) if open.insert(Rc::as_ptr(&map) as *const ()) => {
+        out.push('{');
+        stack.push(Writing::Map(map, 0));
+      }
+      Some(Object::List(_)) => out.push_str("[...]"),
+      Some(Object::Map(_)) => out.push_str("{...}"),
+      _ => {}
+    }
+
+    let Some(top) = stack.last_mut() else {
+      return out;
+    };
+    let finished = match top {
+      Writing::List(items, i) => match items.borrow().get(*i) {
+        Some(item) => {
+          if *i > 0 {
+            out.push_str(", ");
+          }
+          *i += 1;
+          next = element(item, &mut out);
+          false
+        }
+        None => true,
+      },
+      Writing::Map(map, i) => match map.borrow().entry(*i) {
+        Some((key, value)) => {
+          if *i > 0 {
+            out.push_str(", ");
+          }
+          *i += 1;
+          out.push_str(&key.repr());
+          out.push_str(": ");
+          next = element(value, &mut out);
+          false
+        }
+        None => true,
+      },
+    };
+
+    if finished {
+      let (ptr, close) = match stack.pop() {
+        Some(Writing::List(items, _)) => (Rc::as_ptr(&items) as *const (), ']'),
+        Some(Writing::Map(map, _)) => (Rc::as_ptr(&map) as *const (), '}'),
+        None => unreachable!(),
+      };
+      open.remove(&ptr);
+      out.push(close);
+    }
+  }
+}
+
+/// a pair of lists or maps being compared, with how many of their elements have matched so far
+enum Comparing {
+  Lists(Rc<RefCell<Vec<Object>>>, Rc<RefCell<Vec<Object>>>, usize),
+  Maps(Rc<RefCell<LoxMap>>, Rc<RefCell<LoxMap>>, usize),
+}
+
+/// what comparing the next elements of a pair of collections found
+enum Step {
+  Matched,
+  Differs,
+  Open(Object, Object), // both are collections, compared by opening them
+  Finished,             // every element matched
+}
+
+/// compares two lists or maps element by element, walking nested collections with a stack of its
+/// own like `collection_to_string`. a pair already being compared further up is taken as equal,
+/// nothing found so far tells them apart
+fn collections_equal(a: &Object, b: &Object) -> bool {
+  fn elements(a: &Object, b: &Object) -> Step {
+    match (a, b) {
+      (Object::List(_), Object::List(_)) | (Object::Map(_), Object::Map(_)) => {
+        Step::Open(a.clone(), b.clone())
+      }
+      _ if a == b => Step::Matched,
+      _ => Step::Differs,
+    }
+  }
+
+  let mut stack = vec![];
+  let mut open = HashSet::new(); // pairs on the stack
+  let mut next = Some((a.clone(), b.clone()));
+  loop {
+    match next.take() {
+      Some((Object::List(a), Object::List(b))) => {
+        let pair = (Rc::as_ptr(&a) as *const (), Rc::as_ptr(&b) as *const ());
+        if !Rc::ptr_eq(&a, &b) && open.insert(pair) {
+          if a.borrow().len() != b.borrow().len() {
+            return false;
+          }
+          stack.push(Comparing::Lists(a, b, 0));
+        }
+      }
+      Some((Object::Map(a), Object::Map(b))) => {
+        let pair = (Rc::as_ptr(&a) as *const (), Rc::as_ptr(&b) as *const ());
+        if !Rc::ptr_eq(&a, &b) && open.insert(pair) {
+          if a.borrow().len() != b.borrow().len() {
+            return false;
+          }
+          stack.push(Comparing::Maps(a, b, 0));
+        }
+      }
+      Some(_) => return false,
+      None => {}
+    }
+
+    let Some(top) = stack.last_mut() else {
+      return true;
+    };
+    let step = match top {
+      Comparing::Lists(a, b, i) => {
+        let (a, b) = (a.borrow(), b.borrow());
+        *i += 1;
+        match (a.get(*i - 1), b.get(*i - 1)) {
+          (Some(x), Some(y)) => elements(x, y),
+          // the lengths are the same, so both run out together
+          _ => Step::Finished,
+        }
+      }
+      Comparing::Maps(a, b, i) => {
+        let (a, b) = (a.borrow(), b.borrow());
+        *i += 1;
+        match a.entry(*i - 1) {
+          Some((key, x)) => b.get(key).map_or(Step::Differs, |y| elements(x, y)),
+          None => Step::Finished,
+        }
+      }
+    };
+
+    match step {
+      Step::Matched => {}
+      Step::Differs => return false,
+      Step::Open(a, b) => next = Some((a, b)),
+      Step::Finished => {
+        let pair = match stack.pop() {
+          Some(Comparing::Lists(a, b, _)) => {
+            (Rc::as_ptr(&a) as *const (), Rc::as_ptr(&b) as *const ())
+          }
+          Some(Comparing::Maps(a, b, _)) => {
+            (Rc::as_ptr(&a) as *const (), Rc::as_ptr(&b) as *const ())
+          }
+          None => unreachable!(),
+        };
+        open.remove(&pair);
+      }
+    }
+  }
+}
+
+/// map that remembers insertion order, keys must be `Object::is_hashable`
+#[derive(Clone, Default)]
+pub struct LoxMap {
+  entries: Vec<(Object, Object)>,
+  index: HashMap<Object, usize>, // key -> position in entries
+}
+
+impl LoxMap {
+  pub fn get(&self, key: &Object) -> Option<&Object> {
+    self.index.get(key).map(|&i| &self.entries[i].1)
+  }
+
+  pub fn into_entries(self) -> Vec<(Object, Object)> {
+    self.entries
+  }
+
+  /// the `i`th entry in insertion order
+  pub fn entry(&self, i: usize) -> Option<(&Object, &Object)> {
+    self.entries.get(i).map(|(k, v)| (k, v))
+  }
+
+  pub fn contains_key(&self, key: &Object) -> bool {
+    self.index.contains_key(key)
+  }
+
+  /// sets `key` to `value`, returning the value it replaced
+  pub fn insert(&mut self, key: Object, value: Object) -> Option<Object> {
+    match self.index.get(&key) {
+      Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+      None => {
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push((key, value));
+        None
+      }
+    }
+  }
+
+  pub fn remove(&mut self, key: &Object) -> Option<Object> {
+    let i = self.index.remove(key)?;
+    let (_, value) = self.entries.remove(i);
+    self
+      .index
+      .values_mut()
+      .filter(|pos| **pos > i)
+      .for_each(|pos| *pos -= 1);
+    Some(value)
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.entries.capacity()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&Object, &Object)> {
+    self.entries.iter().map(|(k, v)| (k, v))
+  }
+
+  pub fn keys(&self) -> impl Iterator<Item = &Object> {
+    self.entries.iter().map(|(k, _)| k)
+  }
+
+  pub fn values(&self) -> impl Iterator<Item = &Object> {
+    self.entries.iter().map(|(_, v)| v)
+  }
+}
+
 impl fmt::Display for Object {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     write!(
@@ -69,9 +415,14 @@ impl fmt::Display for Object {
       "{}",
       match self {
         Object::String(s) => s.to_string(),
-        Object::Number(n) => n.to_string(),
+        Object::Int(i) => i.to_string(),
+        Object::Number(n) => float_to_string(*n),
+        Object::BigInt(n) => n.to_string(),
+        Object::Decimal(n) => n.to_string(),
         Object::Bool(b) => b.to_string(),
         Object::Callable(c) => c.to_string(),
+        Object::List(_) | Object::Map(_) => collection_to_string(self),
+        Object::Module(module) => module.to_string(),
         Object::None => "none".to_string(),
       }
     )
@@ -82,9 +433,13 @@ impl fmt::Display for Object {
 impl PartialEq for Object {
   fn eq(&self, other: &Self) -> bool {
     match (self, other) {
-      (Object::Number(a), Object::Number(b)) => a == b,
+      (a, b) if a.is_num() && b.is_num() => compare_numbers(a, b) == Some(cmp::Ordering::Equal),
       (Object::String(a), Object::String(b)) => a == b,
       (Object::Bool(a), Object::Bool(b)) => a == b,
+      (Object::List(_), Object::List(_)) | (Object::Map(_), Object::Map(_)) => {
+        collections_equal(self, other)
+      }
+      (Object::Module(a), Object::Module(b)) => Rc::ptr_eq(a, b),
       (Object::None, Object::None) => true,
       (Object::None, _) => false,
       _ => false,
@@ -98,7 +453,7 @@ impl PartialOrd for Object {
   // >, >=, <, <=
   fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
     match (self, other) {
-      (Object::Number(a), Object::Number(b)) => a.partial_cmp(b),
+      (a, b) if a.is_num() && b.is_num() => compare_numbers(a, b),
       (Object::r#String(a), Object::r#String(b)) => a.partial_cmp(b),
       (Object::Bool(a), Object::Bool(b)) => a.partial_cmp(b),
       _ => None,
@@ -109,17 +464,30 @@ impl PartialOrd for Object {
 impl hash::Hash for Object {
   fn hash<H: hash::Hasher>(&self, state: &mut H) {
     use Object::*;
+    // numbers of different kinds that compare equal have to hash the same, so whole numbers hash
+    // as an int and the rest as the closest float, which is the same for equal values
+    if self.is_num() {
+      match self.to_exact_int() {
+        Some(i) => i.hash(state),
+        // every NaN payload is folded into one (NaN is never a valid map key anyway)
+        Option::None => {
+          let n = self.to_num().unwrap();
+          let n = if n.is_nan() { f64::NAN } else { n };
+          n.to_bits().hash(state);
+        }
+      }
+      return;
+    }
+
     std::mem::discriminant(self).hash(state);
     match self {
-      Number(n) => {
-        // for f64, Nan != Nan and can cause issues with Hash/Eq
-        // common approach is to hash the bits directly
-        let bits: u64 = n.to_bits();
-        bits.hash(state);
-      }
+      Int(_) | Number(_) | BigInt(_) | Decimal(_) => {}
       String(s) => s.hash(state),
       Bool(b) => b.hash(state),
       Callable(_c) => {}
+      List(_l) => {}
+      Map(_m) => {}
+      Module(_m) => {}
       None => {}
     }
   }
@@ -140,20 +508,127 @@ impl Object {
     }
   }
 
+  /// the value of any kind of number as the closest float
   pub fn to_num(&self) -> Option<f64> {
     match self {
+      Object::Int(val) => Some(*val as f64),
       Object::Number(val) => Some(*val),
+      Object::BigInt(val) => Some(val.to_f64()),
+      Object::Decimal(val) => Some(val.to_f64()),
       _ => None,
     }
   }
 
   pub fn is_num(&self) -> bool {
     match self {
-      Object::Number(_) => true,
+      Object::Int(_) | Object::Number(_) | Object::BigInt(_) | Object::Decimal(_) => true,
       _ => false,
     }
   }
 
+  /// the value of a whole number of any kind that fits in an i64
+  pub fn to_exact_int(&self) -> Option<i64> {
+    match self {
+      Object::Int(val) => Some(*val),
+      Object::Number(val) => exact_int(*val),
+      Object::BigInt(val) => val.to_i64(),
+      Object::Decimal(val) => val.to_i64(),
+      _ => None,
+    }
+  }
+
+  /// ints and bigints as a bigint
+  pub fn to_bigint(&self) -> Option<BigInt> {
+    match self {
+      Object::Int(val) => Some(BigInt::from(*val)),
+      Object::BigInt(val) => Some(val.as_ref().clone()),
+      _ => None,
+    }
+  }
+
+  /// ints, bigints and decimals as a decimal, floats aren't converted implicitly
+  pub fn to_decimal(&self) -> Option<Decimal> {
+    match self {
+      Object::Decimal(val) => Some(val.as_ref().clone()),
+      other => other.to_bigint().map(Decimal::from),
+    }
+  }
+
+  pub fn to_int(&self) -> Option<i64> {
+    match self {
+      Object::Int(val) => Some(*val),
+      _ => None,
+    }
+  }
+
+  /// approximate number of bytes this value holds itself, used for memory limits. a list's or
+  /// map's elements aren't counted here but charged to the collection, see `limits::Heap`. it
+  /// never changes for a value and its clones, so what's charged for it is what's handed back
+  pub fn size(&self) -> usize {
+    let payload = match self {
+      Object::String(s) => s.len(),
+      Object::BigInt(n) => n.bits() as usize / 8,
+      Object::Decimal(n) => n.bits() as usize / 8,
+      _ => 0,
+    };
+    std::mem::size_of::<Object>() + payload
+  }
+
+  /// drops the value, taking apart the collections no one else holds one at a time. letting them
+  /// drop on their own would recurse once per level of nesting
+  pub fn dismantle(self) {
+    let mut pending = vec![self];
+    while let Some(value) = pending.pop() {
+      match value {
+        Object::List(items) => {
+          if let Ok(items) = Rc::try_unwrap(items) {
+            pending.extend(items.into_inner());
+          }
+        }
+        Object::Map(map) => {
+          if let Ok(map) = Rc::try_unwrap(map) {
+            for (key, value) in map.into_inner().into_entries() {
+              pending.push(key);
+              pending.push(value);
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  /// whether the value can be used as a map key. mutable collections and callables have no stable
+  /// identity to hash and NaN never equals itself, so none of them can
+  pub fn is_hashable(&self) -> bool {
+    match self {
+      Object::Number(n) => !n.is_nan(),
+      Object::Int(_)
+      | Object::BigInt(_)
+      | Object::Decimal(_)
+      | Object::String(_)
+      | Object::Bool(_)
+      | Object::None => true,
+      Object::Callable(_) | Object::List(_) | Object::Map(_) | Object::Module(_) => false,
+    }
+  }
+
+  /// how the value shows up inside a collection, strings are quoted there
+  pub fn repr(&self) -> String {
+    match self {
+      Object::String(s) => format!("{:?}", s),
+      _ => self.to_string(),
+    }
+  }
+
+  pub fn new_list(items: Vec<Object>) -> Object {
+    Object::List(Rc::new(RefCell::new(items)))
+  }
+
+  pub fn new_map(map: LoxMap) -> Object {
+    Object::Map(Rc::new(RefCell::new(map)))
+  }
+
   /// isTruthy() returns false for false and nil and true for everything else
   pub fn to_bool(&self) -> bool {
     match self {
@@ -181,6 +656,8 @@ pub struct Token {
   pub lexeme: String,
   pub literal: Object,
   pub line: usize,
+  pub column: usize, // together with line this tells apart same-named tokens
+  pub file: usize,   // which parsed source the token is from, see modules::next_file
 }
 
 impl fmt::Display for Token {
@@ -188,21 +665,35 @@ impl fmt::Display for Token {
     write!(f, "{:?} '{}' ", self.token_type, self.lexeme)?;
     match &self.literal {
       Object::String(s) => write!(f, "{}", s),
+      Object::Int(i) => write!(f, "{}", i),
       Object::Number(n) => write!(f, "{}", n),
+      Object::BigInt(n) => write!(f, "{}", n),
+      Object::Decimal(n) => write!(f, "{}", n),
       Object::Bool(b) => write!(f, "{}", b),
       Object::Callable(_c) => write!(f, "<fn>"),
+      Object::List(_l) => write!(f, "<list>"),
+      Object::Map(_m) => write!(f, "<map>"),
+      Object::Module(_m) => write!(f, "<module>"),
       Object::None => write!(f, "None"),
     }
   }
 }
 
 impl Token {
-  pub fn new(token_type: TokenType, lexeme: &str, literal: Object, line: usize) -> Self {
+  pub fn new(
+    token_type: TokenType,
+    lexeme: &str,
+    literal: Object,
+    line: usize,
+    column: usize,
+  ) -> Self {
     Self {
       token_type,
       lexeme: lexeme.to_owned(),
       literal,
       line,
+      column,
+      file: 0,
     }
   }
 
@@ -230,6 +721,14 @@ impl AstPrinter {
   }
 }
 
+/// a parameter of a function or lambda
+#[derive(Clone, PartialEq, Hash)]
+pub struct Param {
+  pub name: Token,
+  pub default: Option<Expr>, // evaluated on each call that leaves the argument out
+  pub rest: bool,            // `...name` gathers the remaining positional arguments into a list
+}
+
 // TODO: ideally make this a macro so I can dynamically just define the grammer in a string and
 //  have it expand to this
 #[derive(Clone, PartialEq, Hash)]
@@ -247,6 +746,22 @@ pub enum Expr {
     callee: Box<Expr>,
     paren: Token,
     arguments: Vec<Expr>,
+    named: Vec<(Token, Expr)>, // `name: value` arguments, always after the positional ones
+  },
+  Interpolation {
+    start: Token,     // the string's first part, where errors get reported
+    parts: Vec<Expr>, // text literals and embedded expressions in order
+  },
+  Conditional {
+    condition: Box<Expr>,
+    then_branch: Box<Expr>,
+    else_branch: Box<Expr>,
+  },
+  Compound {
+    target: Box<Expr>, // Variable or Index
+    operator: Token,   // typed as the arithmetic operator applied, e.g. Plus for both += and ++
+    value: Box<Expr>,
+    postfix: bool, // x++ and x-- evaluate to the value from before the update
   },
   Get {
     object: Box<Expr>,
@@ -255,6 +770,30 @@ pub enum Expr {
   Grouping {
     expression: Box<Expr>,
   },
+  Index {
+    object: Box<Expr>,
+    bracket: Token,
+    index: Box<Expr>,
+  },
+  Lambda {
+    keyword: Token, // 'func' or the opening '|'
+    params: Rc<[Param]>,
+    body: Rc<[Stmt]>,
+  },
+  IndexSet {
+    object: Box<Expr>,
+    bracket: Token,
+    index: Box<Expr>,
+    value: Box<Expr>,
+  },
+  List {
+    bracket: Token,
+    elements: Vec<Expr>,
+  },
+  Map {
+    brace: Token,
+    entries: Vec<(Expr, Expr)>,
+  },
   Literal {
     value: Object,
   },
@@ -305,11 +844,44 @@ impl Expr {
         right,
       } => visitor.visit_logical_expr(left, operator, right),
       Expr::Call {
-        callee, arguments, ..
-      } => visitor.visit_call_expr(callee, arguments),
+        callee,
+        arguments,
+        named,
+        ..
+      } => visitor.visit_call_expr(callee, arguments, named),
+      Expr::List { bracket, elements } => visitor.visit_list_expr(bracket, elements),
+      Expr::Map { brace, entries } => visitor.visit_map_expr(brace, entries),
+      Expr::Index {
+        object,
+        bracket,
+        index,
+      } => visitor.visit_index_expr(object, bracket, index),
+      Expr::IndexSet {
+        object,
+        bracket,
+        index,
+        value,
+      } => visitor.visit_index_set_expr(object, bracket, index, value),
+      Expr::Lambda {
+        keyword,
+        params,
+        body,
+      } => visitor.visit_lambda_expr(keyword, params, body),
+      Expr::Get { object, name } => visitor.visit_get_expr(object, name),
+      Expr::Compound {
+        target,
+        operator,
+        value,
+        postfix,
+      } => visitor.visit_compound_expr(target, operator, value, *postfix),
+      Expr::Interpolation { start, parts } => visitor.visit_interpolation_expr(start, parts),
+      Expr::Conditional {
+        condition,
+        then_branch,
+        else_branch,
+      } => visitor.visit_conditional_expr(condition, then_branch, else_branch),
       _ => visitor.visit_literal_expr(&Object::None),
       /*
-      Expr::Get { object, name } => visitor.visit_get_expr(object, name),
       Expr::Set {
           object,
           name,
@@ -330,10 +902,35 @@ pub trait ExprVisitor<T> {
   fn visit_var_expr(&mut self, name: &Token) -> T;
   fn visit_assign_expr(&mut self, name: &Token, value: &Expr) -> T;
   fn visit_logical_expr(&mut self, left: &Expr, operator: &Token, right: &Expr) -> T;
-  fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr]) -> T;
+  fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr], named: &[(Token, Expr)]) -> T;
+  fn visit_list_expr(&mut self, bracket: &Token, elements: &[Expr]) -> T;
+  fn visit_map_expr(&mut self, brace: &Token, entries: &[(Expr, Expr)]) -> T;
+  fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> T;
+  fn visit_index_set_expr(
+    &mut self,
+    object: &Expr,
+    bracket: &Token,
+    index: &Expr,
+    value: &Expr,
+  ) -> T;
+  fn visit_lambda_expr(&mut self, keyword: &Token, params: &Rc<[Param]>, body: &Rc<[Stmt]>) -> T;
+  fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> T;
+  fn visit_compound_expr(
+    &mut self,
+    target: &Expr,
+    operator: &Token,
+    value: &Expr,
+    postfix: bool,
+  ) -> T;
+  fn visit_conditional_expr(
+    &mut self,
+    condition: &Expr,
+    then_branch: &Expr,
+    else_branch: &Expr,
+  ) -> T;
+  fn visit_interpolation_expr(&mut self, start: &Token, parts: &[Expr]) -> T;
 
   /*
-  fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> T;
   fn visit_set_expr(&mut self, object: &Expr, name: &Token, value: &Expr) -> T;
   fn visit_super_expr(&mut self, keyword: &Token, method: &Token) -> T;
   fn visit_this_expr(&mut self, keyword: &Token) -> T;
@@ -352,9 +949,15 @@ impl ExprVisitor<String> for AstPrinter {
   fn visit_literal_expr(&mut self, value: &Object) -> String {
     match value {
       Object::String(s) => s.to_string(),
+      Object::Int(i) => i.to_string(),
       Object::Number(n) => n.to_string(),
+      Object::BigInt(n) => n.to_string(),
+      Object::Decimal(n) => n.to_string(),
       Object::Bool(b) => b.to_string(),
       Object::Callable(c) => c.to_string(),
+      Object::List(_l) => value.to_string(),
+      Object::Map(_m) => value.to_string(),
+      Object::Module(_m) => value.to_string(),
       Object::None => "None".to_string(),
     }
   }
@@ -375,16 +978,97 @@ impl ExprVisitor<String> for AstPrinter {
     self.parenthesize(&operator.lexeme, &[left, right])
   }
 
-  fn visit_call_expr(&mut self, callee: &Expr, arguments: &[Expr]) -> String {
+  fn visit_call_expr(
+    &mut self,
+    callee: &Expr,
+    arguments: &[Expr],
+    named: &[(Token, Expr)],
+  ) -> String {
     let mut result = format!("(call {}", callee.accept(self));
     for arg in arguments {
       result.push(' ');
       result.push_str(&arg.accept(self));
     }
+    for (name, arg) in named {
+      result.push_str(&format!(" ({}: {})", name.lexeme, arg.accept(self)));
+    }
     result.push(')');
     result
   }
 
+  fn visit_list_expr(&mut self, _bracket: &Token, elements: &[Expr]) -> String {
+    let elements: Vec<&Expr> = elements.iter().collect();
+    self.parenthesize("list", &elements)
+  }
+
+  fn visit_map_expr(&mut self, _brace: &Token, entries: &[(Expr, Expr)]) -> String {
+    let mut result = "(map".to_string();
+    for (key, value) in entries {
+      result.push_str(&format!(" ({} {})", key.accept(self), value.accept(self)));
+    }
+    result.push(')');
+    result
+  }
+
+  fn visit_lambda_expr(
+    &mut self,
+    _keyword: &Token,
+    params: &Rc<[Param]>,
+    _body: &Rc<[Stmt]>,
+  ) -> String {
+    let params: Vec<String> = params
+      .iter()
+      .map(|p| match &p.default {
+        Some(default) => format!("(= {} {})", p.name.lexeme, default.accept(self)),
+        None if p.rest => format!("...{}", p.name.lexeme),
+        None => p.name.lexeme.clone(),
+      })
+      .collect();
+    format!("(lambda ({}) ...)", params.join(" "))
+  }
+
+  fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> String {
+    format!("(. {} {})", object.accept(self), name.lexeme)
+  }
+
+  fn visit_compound_expr(
+    &mut self,
+    target: &Expr,
+    operator: &Token,
+    value: &Expr,
+    _postfix: bool,
+  ) -> String {
+    self.parenthesize(&operator.lexeme, &[target, value])
+  }
+
+  fn visit_conditional_expr(
+    &mut self,
+    condition: &Expr,
+    then_branch: &Expr,
+    else_branch: &Expr,
+  ) -> String {
+    self.parenthesize("?:", &[condition, then_branch, else_branch])
+  }
+
+  fn visit_interpolation_expr(&mut self, _start: &Token, parts: &[Expr]) -> String {
+    let parts: Vec<&Expr> = parts.iter().collect();
+    self.parenthesize("str", &parts)
+  }
+
+  fn visit_index_expr(&mut self, object: &Expr, _bracket: &Token, index: &Expr) -> String {
+    self.parenthesize("index", &[object, index])
+  }
+
+  fn visit_index_set_expr(
+    &mut self,
+    object: &Expr,
+    _bracket: &Token,
+    index: &Expr,
+    value: &Expr,
+  ) -> String {
+    self.parenthesize("index=", &[object, index, value])
+  }
+
   /*
   fn visit_get_expr(&self, object: &Expr, name: &Token) -> String {
       format!("(. {} {})", object.accept(self), name.lexeme)
@@ -415,7 +1099,7 @@ impl fmt::Display for Expr {
   }
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Hash)]
 pub enum Stmt {
   Block {
     statements: Vec<Stmt>,
@@ -430,8 +1114,9 @@ pub enum Stmt {
   },
   Function {
     name: Token,
-    params: Vec<Token>,
-    body: Vec<Stmt>,
+    params: Rc<[Param]>, // shared between the ast and every function value made from it
+    body: Rc<[Stmt]>,
+    doc: Option<String>, // from the /// comment lines right before it
   },
   If {
     condition: Expr,
@@ -448,10 +1133,42 @@ pub enum Stmt {
   Var {
     name: Token,
     initializer: Option<Expr>,
+    constant: bool, // declared with const, so it can never be assigned again
+    doc: Option<String>,
   },
   While {
     condition: Expr,
     body: Box<Stmt>,
+    increment: Option<Expr>, // a desugared for loop's increment, still run after a continue
+    label: Option<Token>,
+  },
+  ForIn {
+    name: Token,
+    iterable: Expr,
+    body: Box<Stmt>,
+    label: Option<Token>,
+  },
+  Break {
+    keyword: Token,
+    label: Option<Token>,
+  },
+  Continue {
+    keyword: Token,
+    label: Option<Token>,
+  },
+  Throw {
+    keyword: Token,
+    value: Expr,
+  },
+  Try {
+    body: Vec<Stmt>,
+    catch: Option<(Token, Vec<Stmt>)>, // name the caught error is bound to, handler
+    finally: Option<Vec<Stmt>>,
+  },
+  Import {
+    path: Token,
+    alias: Option<Token>, // import "path" as alias;
+    names: Vec<Token>,    // from "path" import names;
   },
 }
 
@@ -460,15 +1177,42 @@ impl Stmt {
     match self {
       Stmt::Expression { expression } => visitor.visit_expression_stmt(expression),
       Stmt::Print { expression } => visitor.visit_print_stmt(expression),
-      Stmt::Var { name, initializer } => visitor.visit_var_stmt(name, initializer),
+      Stmt::Var {
+        name,
+        initializer,
+        constant,
+        ..
+      } => visitor.visit_var_stmt(name, initializer, *constant),
       Stmt::Block { statements } => visitor.visit_block_stmt(statements),
       Stmt::If {
         condition,
         then_branch,
         else_branch,
       } => visitor.visit_if_stmt(condition, then_branch, else_branch),
-      Stmt::While { condition, body } => visitor.visit_while_stmt(condition, body),
-      Stmt::Function { name, params, body } => visitor.visit_function_stmt(name, params, body),
+      Stmt::While {
+        condition,
+        body,
+        increment,
+        label,
+      } => visitor.visit_while_stmt(condition, body, increment, label),
+      Stmt::ForIn {
+        name,
+        iterable,
+        body,
+        label,
+      } => visitor.visit_for_in_stmt(name, iterable, body, label),
+      Stmt::Break { keyword, label } => visitor.visit_break_stmt(keyword, label),
+      Stmt::Continue { keyword, label } => visitor.visit_continue_stmt(keyword, label),
+      Stmt::Throw { keyword, value } => visitor.visit_throw_stmt(keyword, value),
+      Stmt::Try {
+        body,
+        catch,
+        finally,
+      } => visitor.visit_try_stmt(body, catch, finally),
+      Stmt::Import { path, alias, names } => visitor.visit_import_stmt(path, alias, names),
+      Stmt::Function {
+        name, params, body, ..
+      } => visitor.visit_function_stmt(name, params, body),
       Stmt::Return { keyword, value } => visitor.visit_return_stmt(keyword, value),
       _ => visitor.visit_expression_stmt(&Expr::Literal {
         value: Object::None,
@@ -483,7 +1227,7 @@ impl Stmt {
 pub trait StmtVisitor<T> {
   fn visit_expression_stmt(&mut self, expression: &Expr) -> T;
   fn visit_print_stmt(&mut self, expression: &Expr) -> T;
-  fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> T;
+  fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>, constant: bool) -> T;
   fn visit_block_stmt(&mut self, statements: &[Stmt]) -> T;
   fn visit_if_stmt(
     &mut self,
@@ -491,8 +1235,31 @@ pub trait StmtVisitor<T> {
     then_branch: &Stmt,
     else_branch: &Option<Stmt>,
   ) -> T;
-  fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> T;
-  fn visit_function_stmt(&mut self, name: &Token, params: &Vec<Token>, body: &Vec<Stmt>) -> T;
+  fn visit_while_stmt(
+    &mut self,
+    condition: &Expr,
+    body: &Stmt,
+    increment: &Option<Expr>,
+    label: &Option<Token>,
+  ) -> T;
+  fn visit_for_in_stmt(
+    &mut self,
+    name: &Token,
+    iterable: &Expr,
+    body: &Stmt,
+    label: &Option<Token>,
+  ) -> T;
+  fn visit_break_stmt(&mut self, keyword: &Token, label: &Option<Token>) -> T;
+  fn visit_continue_stmt(&mut self, keyword: &Token, label: &Option<Token>) -> T;
+  fn visit_throw_stmt(&mut self, keyword: &Token, value: &Expr) -> T;
+  fn visit_try_stmt(
+    &mut self,
+    body: &[Stmt],
+    catch: &Option<(Token, Vec<Stmt>)>,
+    finally: &Option<Vec<Stmt>>,
+  ) -> T;
+  fn visit_import_stmt(&mut self, path: &Token, alias: &Option<Token>, names: &[Token]) -> T;
+  fn visit_function_stmt(&mut self, name: &Token, params: &Rc<[Param]>, body: &Rc<[Stmt]>) -> T;
   fn visit_return_stmt(&mut self, keyword: &Token, value: &Option<Expr>) -> T;
 
   // fn visit_class_stmt(&mut self, name: &Token, superclass: &Expr, methods: &Vec<Stmt>) -> T;
@@ -504,7 +1271,7 @@ impl StmtVisitor<String> for AstPrinter {
 
     fn visit_print_stmt(&mut self, expression: &Expr) -> String {}
 
-    fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>) -> String {}
+    fn visit_var_stmt(&mut self, name: &Token, initializer: &Option<Expr>, constant: bool) -> String {}
 
     fn visit_block_stmt(&mut self, statements: &[Stmt]) -> String {}
 